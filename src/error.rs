@@ -0,0 +1,39 @@
+//! ## crate::error
+//!
+//! This mod contains the crate's `Error` type, returned by the fallible IO traits
+//! (`TryReadableIO`, `TryWritableIO` and `TryReadWritableIO`).
+//!
+//! `E` is the error type of the bus a register sits on. Registers that can't fail
+//! (e.g. memory-mapped ones) use `core::convert::Infallible`.
+
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error<E = ()> {
+    /// The bus transfer failed.
+    Bus(E),
+    /// A value doesn't fit in the target bit field.
+    Overflow,
+    /// The register didn't reach the expected state in time.
+    Timeout,
+    /// The value read back differs from the value written.
+    VerifyMismatch {
+        written: u64,
+        read: u64
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Bus(e) => write!(f, "bus error: {}", e),
+            Error::Overflow => write!(f, "value doesn't fit in the bit field"),
+            Error::Timeout => write!(f, "timed out waiting for the register"),
+            Error::VerifyMismatch { written, read } => {
+                write!(f, "wrote {:#x} but read back {:#x}", written, read)
+            }
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> core::error::Error for Error<E> {}
@@ -2,9 +2,15 @@
 //!
 //! This mod contains `UIntLike` trait to group available integer types for a register.
 //! It also contains two useful method `zero()`, which returns 0, and `full()`, which
-//! returns the maximun number of given type.
+//! returns the maximun number of given type. `to_u64()` and `from_u64()` convert between
+//! the type and `u64`, which is handy when a backend stores values of any width.
+//! `swap_bytes()` reverses the byte order, for registers of a foreign byte order.
 //!
 //! This crate currently supports 8-bit, 16-bit, 32-bit and 64-bit registers.
+//!
+//! `UIntLike` is sealed, so methods can be added to it without breaking other crates.
+//! Implementing it outside this crate, which was possible before `to_u64()` was
+//! added, is no longer allowed.
 
 use core::ops::{
     BitAnd,
@@ -18,8 +24,13 @@ use core::ops::{
     Shr
 };
 
+mod sealed {
+    pub trait Sealed {}
+}
+
 pub trait UIntLike :
-    BitAnd<Output = Self>
+    sealed::Sealed
+    + BitAnd<Output = Self>
     + BitAndAssign
     + BitOr<Output = Self>
     + BitOrAssign
@@ -34,10 +45,16 @@ pub trait UIntLike :
 {
     fn zero() -> Self;
     fn all() -> Self;
+    fn to_u64(self) -> u64;
+    /// Convert from `u64`, higher bits that don't fit are dropped.
+    fn from_u64(val: u64) -> Self;
+    fn swap_bytes(self) -> Self;
 }
 
 macro_rules! impl_uintlike_zero {
     ($typ:ty) => {
+        impl sealed::Sealed for $typ {}
+
         impl UIntLike for $typ {
             #[inline]
             fn zero() -> Self {
@@ -47,6 +64,18 @@ macro_rules! impl_uintlike_zero {
             fn all() -> Self {
                 (0. as $typ).wrapping_sub(1)
             }
+            #[inline]
+            fn to_u64(self) -> u64 {
+                self as u64
+            }
+            #[inline]
+            fn from_u64(val: u64) -> Self {
+                val as $typ
+            }
+            #[inline]
+            fn swap_bytes(self) -> Self {
+                <$typ>::swap_bytes(self)
+            }
         }
     };
 }
@@ -7,20 +7,69 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
+mod error;
 mod int;
 mod register;
 
+pub use error::Error;
+
+#[doc(hidden)]
+pub use register::bank::select_field as __select_bank_field;
+#[doc(hidden)]
+pub use register::transaction::run_layout as __run_layout_transaction;
+
 pub mod prelude {
     pub use core::marker::PhantomData;
 
     pub use crate::registers_layout;
+    pub use crate::bus_registers;
+    pub use crate::indirect_registers;
     pub use crate::reg_bitfields;
 
     pub use crate::int::UIntLike;
 
     pub use crate::register::bitfield::BitsLike;
+    pub use crate::register::bitfield::BitField;
     pub use crate::register::bitfield::Bits;
-    pub use crate::register::RegName;
+    pub use crate::register::bitfield::LocalCopy;
+    pub use crate::register::bitfield::MaskedVal;
+    pub use crate::register::{RegName, FieldDesc, FieldValue, RegInfo};
+    pub use crate::register::trace::{Access, Direction};
+    #[cfg(feature = "trace")]
+    pub use crate::register::trace::{add_layout, set_tracer};
+    #[cfg(feature = "std")]
+    pub use crate::register::trace::print_tracer;
+    pub use crate::register::RegisterLayout;
+    pub use crate::register::fake::FakeDevice;
+    #[cfg(feature = "std")]
+    pub use crate::register::sim::SimDevice;
+    #[cfg(feature = "std")]
+    pub use crate::register::replay::{Event, Script, ScriptError, Recorder, Replayer};
+    #[cfg(feature = "std")]
+    pub use crate::register::mock::MockDevice;
+    #[cfg(feature = "std")]
+    pub use crate::register::coverage::{Coverage, CoverageReport, RegCoverage};
+    pub use crate::register::{InnerRegister, Readable, Writable};
+    pub use crate::register::backend::{RegisterBackend, Mmio, Device, RegDesc, BackedRegister};
+    pub use crate::register::barrier::{Barrier, AtomicBarrier, Fenced};
+    pub use crate::register::bitband::BitBand;
+    pub use crate::register::bus::{RegisterBus, RegisterMap, BusDevice, BusRegister};
+    pub use crate::register::cache::RegCache;
+    pub use crate::register::transaction::{Transaction, LayoutTransaction};
+    pub use crate::register::lock::{CriticalSection, NoLock, GlobalLock, Locked, LockHook, set_global_lock};
+    #[cfg(feature = "std")]
+    pub use crate::register::lock::MutexLock;
+    pub use crate::register::indirect::{IndirectMap, IndirectSpace, IndirectRegister};
+    pub use crate::register::bank::{Bank, BankSelect, BankGuard};
+    pub use crate::register::async_bus::{AsyncRegisterBus, AsyncBusDevice, AsyncBusRegister};
+    pub use crate::register::async_bus::{AsyncIO, AsyncReadableIO, AsyncWritableIO, AsyncReadWritableIO};
     pub use crate::register::{ROInnerRegister, WOInnerRegister, RWInnerRegister};
+    pub use crate::register::{WOSInnerRegister, Shadowed, ShadowTarget};
+    pub use crate::register::RCInnerRegister;
+    pub use crate::register::atomic::{AtomicInnerRegister, AtomicUInt};
+    pub use crate::register::alias::AliasedInnerRegister;
+    pub use crate::register::split::{SplitInnerRegister, SplitOrder, HighFirst, LowFirst};
+    pub use crate::register::endian::{Endianness, NativeEndian, BigEndian, LittleEndian};
     pub use crate::register::bitfield::{ReadableIO, WritableIO, ReadWritableIO};
+    pub use crate::register::fallible::{InfallibleIO, TryIO, TryReadableIO, TryWritableIO, TryReadWritableIO};
 }
@@ -1,72 +1,446 @@
 //! ## crate::register
 //!
-//! This mod contains built-in register utilities. There are Read-Only, Write-Only and
-//! Read-Write `InnerRegister` structs.
+//! This mod contains built-in register utilities. There are Read-Only, Read-to-Clear,
+//! Write-Only, Write-Only Shadowed and Read-Write `InnerRegister` structs.
+//!
+//! Every kind takes a byte order parameter `E`, see `register::endian` mod.
 //!
 //! Users should not use these structs directly. Please use `registers_layout!{}`
 //! to generate the layout.
 
+/// Trace an access of a built-in register kind, see `register::trace` mod. With
+/// `at`, the access covers the part of the register `at` bytes into it.
+macro_rules! trace {
+    ($dir:ident, $reg:expr, $val:expr) => {{
+        #[cfg(feature = "trace")]
+        $crate::register::trace::trace(
+            $crate::register::trace::Direction::$dir, $reg as *const _ as usize, 0, $val,
+            <R as $crate::register::RegName>::FIELDS
+        );
+    }};
+    ($dir:ident, $reg:expr, at $at:expr, $val:expr) => {{
+        #[cfg(feature = "trace")]
+        $crate::register::trace::trace(
+            $crate::register::trace::Direction::$dir, $reg as *const _ as usize, $at, $val, &[]
+        );
+    }};
+}
+
+pub mod alias;
+pub mod async_bus;
+pub mod atomic;
+pub mod backend;
+pub mod bank;
+pub mod barrier;
+pub mod bitband;
 pub mod bitfield;
+pub mod bus;
+pub mod cache;
+#[cfg(feature = "std")]
+pub mod coverage;
+pub mod endian;
+pub mod fake;
+pub mod fallible;
+pub mod indirect;
+pub mod lock;
 pub mod macros;
+#[cfg(feature = "std")]
+pub mod mock;
+#[cfg(feature = "std")]
+pub mod replay;
+#[cfg(feature = "std")]
+pub mod sim;
+pub mod split;
+pub mod trace;
+pub mod transaction;
 
-use core::ptr;
-use core::cell::UnsafeCell;
+use core::mem::size_of;
+use core::cell::{Cell, UnsafeCell};
 use core::marker::PhantomData;
 
 use crate::int::UIntLike;
 
-use bitfield::{BitsLike, ReadableIO, WritableIO};
+use endian::{Endianness, NativeEndian};
+use bitfield::{BitField, Bits, BitsLike, LocalCopy, MaskedVal, ReadableIO, WritableIO, ReadWritableIO};
+use fallible::InfallibleIO;
+use lock::rmw;
+
+/// Implemented by register names generated by `reg_bitfields!{}`, which also fill in
+/// the register's metadata for tracing.
+pub trait RegName {
+    const NAME: &'static str = "";
+    const FIELDS: &'static [FieldDesc] = &[];
+}
+
+/// ## Bit field description
+#[derive(Clone, Copy, Debug)]
+pub struct FieldDesc {
+    pub name: &'static str,
+    pub offset: u8,
+    pub width: u8,
+    /// The named values of the field.
+    pub values: &'static [(&'static str, u64)]
+}
+
+impl FieldDesc {
+    /// The field's value in register value `reg`.
+    #[inline]
+    pub fn decode(&self, reg: u64) -> u64 {
+        (reg & self.mask()) >> self.offset
+    }
+
+    /// The field's bits in a register value.
+    #[inline]
+    pub fn mask(&self) -> u64 {
+        (u64::MAX >> (64 - self.width as u32)) << self.offset
+    }
+
+    /// The name of the field's value `val`, if it is a named value.
+    #[inline]
+    pub fn value_name(&self, val: u64) -> Option<&'static str> {
+        self.values.iter().find(|(_, v)| *v == val).map(|(name, _)| *name)
+    }
+
+    /// The field's value in register value `reg`, displayed by name if it has one
+    /// and in hex otherwise.
+    #[inline]
+    pub fn display(&self, reg: u64) -> FieldValue {
+        let val = self.decode(reg);
+        FieldValue { name: self.value_name(val), val }
+    }
+}
+
+/// A field's value, see `FieldDesc::display()`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FieldValue {
+    pub name: Option<&'static str>,
+    pub val: u64
+}
 
-pub trait RegName {}
+impl core::fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.name {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "{:#x}", self.val)
+        }
+    }
+}
 
 impl RegName for () {}
 
+/// Implemented by every `InnerRegister` kind, so other register implementations
+/// (e.g. `backend::BackedRegister`) can tell its width, register name and byte order.
+pub trait InnerRegister {
+    type Int: UIntLike;
+    type Name: RegName + BitsLike<Self::Int>;
+    type Endian: Endianness;
+}
+
+/// ## Register layout
+///
+/// Implemented by the layouts generated by `registers_layout!{}`.
+///
+/// # Safety
+///
+/// All zero bytes must be a valid value of the layout, and every byte of it must be
+/// interior-mutable, so its memory can be changed while the layout is borrowed.
+pub unsafe trait RegisterLayout: Sized {
+    /// What diagnostics know of the register at `offset`.
+    fn reg_info(_offset: usize) -> Option<RegInfo> {
+        None
+    }
+
+    /// The register at `offset`, if it is an `I`.
+    fn reg_ref<I: 'static>(&self, _offset: usize) -> Option<&I> {
+        None
+    }
+}
+
+/// ## Register info
+///
+/// A layout register's name, bit fields and byte order, see
+/// `RegisterLayout::reg_info()`.
+#[derive(Clone, Copy, Debug)]
+pub struct RegInfo {
+    /// The register's name in the layout.
+    pub name: &'static str,
+    /// The register's width in bytes.
+    pub width: usize,
+    pub fields: &'static [FieldDesc],
+    /// Converts a value as a backend sees it, in hardware byte order, to logical bit
+    /// order.
+    pub from_hw: fn(u64) -> u64
+}
+
+impl RegInfo {
+    /// The info of register `name` of kind `I`.
+    pub fn of<I: InnerRegister>(name: &'static str) -> Self {
+        Self {
+            name,
+            width: size_of::<I::Int>(),
+            fields: <I::Name as RegName>::FIELDS,
+            from_hw: |val| I::Endian::from_hw(I::Int::from_u64(val)).to_u64()
+        }
+    }
+
+    /// The logical value of a backend access of `width` bytes with value `raw`,
+    /// `None` if the access doesn't cover the whole register.
+    #[inline]
+    pub fn decode(&self, width: usize, raw: u64) -> Option<u64> {
+        (width == self.width).then(|| (self.from_hw)(raw))
+    }
+}
+
+/// Marks `InnerRegister` kinds that can be read freely.
+pub trait Readable: InnerRegister {}
+
+/// Marks `InnerRegister` kinds that can be written freely.
+pub trait Writable: InnerRegister {}
+
+macro_rules! impl_inner_register {
+    ($reg:ident$(: $($marker:ident),+)?) => {
+        impl<T, R, E> InnerRegister for $reg<T, R, E>
+        where
+            T: UIntLike,
+            R: RegName + BitsLike<T>,
+            E: Endianness
+        {
+            type Int = T;
+            type Name = R;
+            type Endian = E;
+        }
+        $($(
+            impl<T, R, E> $marker for $reg<T, R, E>
+            where
+                T: UIntLike,
+                R: RegName + BitsLike<T>,
+                E: Endianness
+            {}
+        )+)?
+    };
+}
+
+impl_inner_register!(ROInnerRegister: Readable, InfallibleIO);
+impl_inner_register!(RCInnerRegister);
+impl_inner_register!(WOInnerRegister: Writable, InfallibleIO);
+impl_inner_register!(WOSInnerRegister);
+impl_inner_register!(RWInnerRegister: Readable, Writable, InfallibleIO);
+
 /// ## Read-Only register
 #[repr(transparent)]
-pub struct ROInnerRegister<T, R = ()>
+pub struct ROInnerRegister<T, R = (), E = NativeEndian>
 where
     T: UIntLike,
-    R: RegName + BitsLike<T>
+    R: RegName + BitsLike<T>,
+    E: Endianness
 {
-    raw: T,
-    _reg: PhantomData<R>
+    raw: UnsafeCell<T>,
+    _reg: PhantomData<(R, E)>
 }
 
-impl<T, R> ReadableIO<T, R> for ROInnerRegister<T, R>
+impl<T, R, E> ReadableIO<T, R> for ROInnerRegister<T, R, E>
 where
     T: UIntLike,
-    R: RegName + BitsLike<T>
+    R: RegName + BitsLike<T>,
+    E: Endianness
 {
     #[inline]
     fn read(&self) -> T {
-        unsafe {
-            ptr::read_volatile(&self.raw)
-        }
+        let val = E::from_hw(unsafe {
+            self.raw.get().read_volatile()
+        });
+        trace!(Read, self, val);
+        val
+    }
+}
+
+/// ## Read-to-Clear register
+///
+/// Reading this kind of register has side effects (e.g. it pops a FIFO or clears
+/// status flags), so it doesn't implement `ReadableIO`. The only way to read it is
+/// `take()`, which performs exactly one hardware read and returns a `LocalCopy` to
+/// decode fields from.
+///
+/// ```compile_fail
+/// use re_reg::prelude::*;
+/// reg_bitfields! { SR(u32) [ RXNE [ 0 => 1 ] ] }
+/// registers_layout! { Regs { ( 0x00 => SR: RC<u32, SR::Reg> ), @END } }
+/// fn poll(regs: &Regs) -> bool {
+///     regs.SR.is_set(SR::RXNE)
+/// }
+/// ```
+#[repr(transparent)]
+pub struct RCInnerRegister<T, R = (), E = NativeEndian>
+where
+    T: UIntLike,
+    R: RegName + BitsLike<T>,
+    E: Endianness
+{
+    raw: UnsafeCell<T>,
+    _reg: PhantomData<(R, E)>
+}
+
+impl<T, R, E> RCInnerRegister<T, R, E>
+where
+    T: UIntLike,
+    R: RegName + BitsLike<T>,
+    E: Endianness
+{
+    /// Read the register once.
+    #[inline]
+    #[must_use = "the read has side effects, the value can't be read again"]
+    pub fn take(&self) -> LocalCopy<T, R> {
+        let val = E::from_hw(unsafe {
+            self.raw.get().read_volatile()
+        });
+        trace!(Read, self, val);
+        LocalCopy::new(val)
     }
 }
 
 /// ## Write-Only register
 #[repr(transparent)]
-pub struct WOInnerRegister<T: UIntLike, R = ()>
+pub struct WOInnerRegister<T, R = (), E = NativeEndian>
 where
     T: UIntLike,
-    R: RegName + BitsLike<T>
+    R: RegName + BitsLike<T>,
+    E: Endianness
 {
     raw: UnsafeCell<T>,
-    _reg: PhantomData<R>
+    _reg: PhantomData<(R, E)>
 }
 
-impl<T, R> WritableIO<T, R> for WOInnerRegister<T, R>
+impl<T, R, E> WritableIO<T, R> for WOInnerRegister<T, R, E>
 where
     T: UIntLike,
-    R: RegName + BitsLike<T>
+    R: RegName + BitsLike<T>,
+    E: Endianness
 {
     #[inline]
     fn write(&self, val: T) {
+        trace!(Write, self, val);
+        unsafe {
+            self.raw.get().write_volatile(E::to_hw(val));
+        }
+    }
+}
+
+/// ## Write-Only Shadowed register
+///
+/// A write-only register which keeps its last written value in a software shadow copy.
+/// The register can't be written directly, otherwise the shadow would go stale. Call
+/// `shadowed()` once to get a `Shadowed` handle and do all the writes through it.
+#[repr(transparent)]
+pub struct WOSInnerRegister<T, R = (), E = NativeEndian>
+where
+    T: UIntLike,
+    R: RegName + BitsLike<T>,
+    E: Endianness
+{
+    raw: UnsafeCell<T>,
+    _reg: PhantomData<(R, E)>
+}
+
+impl<T, R, E> WOSInnerRegister<T, R, E>
+where
+    T: UIntLike,
+    R: RegName + BitsLike<T>,
+    E: Endianness
+{
+    /// Create the shadowed handle of this register. `init` should be the value the
+    /// hardware currently holds, which is usually the reset value.
+    #[inline]
+    pub fn shadowed(&self, init: T) -> Shadowed<&Self> {
+        Shadowed::new(self, init)
+    }
+}
+
+/// Implemented by the write-only shadowed registers a `Shadowed` handle writes to,
+/// i.e. `&WOSInnerRegister` and `backend::BackedRegister` of it.
+pub trait ShadowTarget {
+    type Int: UIntLike;
+    type Name: RegName + BitsLike<Self::Int>;
+
+    /// Write the hardware, leaving the shadow alone.
+    fn write_raw(&self, val: Self::Int);
+}
+
+impl<T, R, E> ShadowTarget for &WOSInnerRegister<T, R, E>
+where
+    T: UIntLike,
+    R: RegName + BitsLike<T>,
+    E: Endianness
+{
+    type Int = T;
+    type Name = R;
+
+    #[inline]
+    fn write_raw(&self, val: T) {
+        trace!(Write, *self, val);
         unsafe {
-            self.raw.get().write_volatile(val);
+            self.raw.get().write_volatile(E::to_hw(val));
+        }
+    }
+}
+
+/// ## Shadowed write-only register handle
+///
+/// Every write updates both the shadow copy and the hardware, so `put_back()`,
+/// `set_back()` and `clear()` work like their `ReadWritableIO` counterparts, taking
+/// the shadow as the current value instead of reading the hardware.
+pub struct Shadowed<U: ShadowTarget> {
+    reg: U,
+    shadow: Cell<U::Int>
+}
+
+impl<U: ShadowTarget> Shadowed<U> {
+    #[inline]
+    pub(crate) fn new(reg: U, init: U::Int) -> Self {
+        Self {
+            reg,
+            shadow: Cell::new(init)
         }
     }
+
+    /// The last value written to the register.
+    #[inline]
+    pub fn shadow(&self) -> U::Int {
+        self.shadow.get()
+    }
+
+    /// Rewrite the shadow value to the hardware, e.g. after the device lost its state.
+    #[inline]
+    pub fn sync(&self) {
+        self.reg.write_raw(self.shadow.get());
+    }
+
+    /// Put the value into target bits, while keeping others as in the shadow.
+    #[inline]
+    pub fn put_back(&self, val: MaskedVal<U::Int, U::Name>) {
+        rmw(|| self.write(self.shadow.get() & (!val.mask) | val.val));
+    }
+
+    /// Set target bits, while keeping others as in the shadow.
+    #[inline]
+    pub fn set_back(&self, bits: Bits<U::Int, U::Name>) {
+        rmw(|| self.write(self.shadow.get() | bits.mask));
+    }
+
+    /// Set target bits to 0, while keeping others as in the shadow.
+    #[inline]
+    pub fn clear(&self, bits: Bits<U::Int, U::Name>) {
+        rmw(|| self.write(self.shadow.get() & (!bits.mask)));
+    }
+}
+
+impl<U: ShadowTarget> InfallibleIO for Shadowed<U> {}
+
+impl<U: ShadowTarget> WritableIO<U::Int, U::Name> for Shadowed<U> {
+    #[inline]
+    fn write(&self, val: U::Int) {
+        self.shadow.set(val);
+        self.reg.write_raw(val);
+    }
 }
 
 /// ## Read-Write register
@@ -75,38 +449,174 @@ where
 /// detault `put()` method. If an IO is both readable and writable, we always retain
 /// other bits' value when we manipulate some bits. Therefore we re-write this method
 /// here to keep things this way.
+///
+/// For hardware where a full-width write has side effects on untouched bytes, the
+/// register can also be accessed by lanes: `read_lane()`/`write_lane()` access one
+/// narrower aligned part, and `put_narrow::<F>()` puts field `F` with the smallest lane
+/// covering it. Lane 0 holds the least significant bits, whatever the byte order.
 #[repr(transparent)]
-pub struct RWInnerRegister<T, R = ()>
+pub struct RWInnerRegister<T, R = (), E = NativeEndian>
 where
     T: UIntLike,
-    R: RegName + BitsLike<T>
+    R: RegName + BitsLike<T>,
+    E: Endianness
 {
     raw: UnsafeCell<T>,
-    _reg: PhantomData<R>
+    _reg: PhantomData<(R, E)>
 }
 
-impl<T, R> ReadableIO<T, R> for RWInnerRegister<T, R>
+impl<T, R, E> ReadableIO<T, R> for RWInnerRegister<T, R, E>
 where
     T: UIntLike,
-    R: RegName + BitsLike<T>
+    R: RegName + BitsLike<T>,
+    E: Endianness
 {
     #[inline]
     fn read(&self) -> T {
+        let val = E::from_hw(unsafe {
+            self.raw.get().read_volatile()
+        });
+        trace!(Read, self, val);
+        val
+    }
+}
+
+impl<T, R, E> WritableIO<T, R> for RWInnerRegister<T, R, E>
+where
+    T: UIntLike,
+    R: RegName + BitsLike<T>,
+    E: Endianness
+{
+    #[inline]
+    fn write(&self, val: T) {
+        trace!(Write, self, val);
         unsafe {
-            self.raw.get().read()
+            self.raw.get().write_volatile(E::to_hw(val));
         }
     }
 }
 
-impl<T, R> WritableIO<T, R> for RWInnerRegister<T, R>
+impl<T, R, E> RWInnerRegister<T, R, E>
 where
     T: UIntLike,
-    R: RegName + BitsLike<T>
+    R: RegName + BitsLike<T>,
+    E: Endianness
 {
     #[inline]
-    fn write(&self, val: T) {
+    fn lane_ptr<N: UIntLike>(&self, index: usize) -> *mut N {
+        let lanes = size_of::<T>() / size_of::<N>();
+        assert!(index < lanes, "Lane index out of range");
+        let pos = if E::BIG_ENDIAN { lanes - 1 - index } else { index };
         unsafe {
-            self.raw.get().write_volatile(val);
+            (self.raw.get() as *mut u8).add(pos * size_of::<N>()) as *mut N
+        }
+    }
+
+    #[inline]
+    fn read_lane_raw<N: UIntLike>(&self, index: usize) -> N {
+        let val = E::from_hw(unsafe {
+            self.lane_ptr::<N>(index).read_volatile()
+        });
+        trace!(Read, self, at index * size_of::<N>(), val);
+        val
+    }
+
+    #[inline]
+    fn write_lane_raw<N: UIntLike>(&self, index: usize, val: N) {
+        trace!(Write, self, at index * size_of::<N>(), val);
+        unsafe {
+            self.lane_ptr::<N>(index).write_volatile(E::to_hw(val));
+        }
+    }
+
+    /// Read lane `index` of width `N`, without touching the other lanes.
+    #[inline]
+    pub fn read_lane<N: UIntLike>(&self, index: usize) -> N {
+        const {
+            assert!(size_of::<N>() < size_of::<T>(), "A lane must be narrower than its register");
+        }
+        self.read_lane_raw(index)
+    }
+
+    /// Write lane `index` of width `N`, without touching the other lanes. `N` must
+    /// be narrower than the register:
+    ///
+    /// ```compile_fail
+    /// use re_reg::prelude::*;
+    /// registers_layout! { Regs { ( 0x00 => DR: RW<u16> ), @END } }
+    /// let regs = unsafe { &*(0x1000 as *const Regs) };
+    /// regs.DR.write_lane::<u32>(0, 1);
+    /// ```
+    #[inline]
+    pub fn write_lane<N: UIntLike>(&self, index: usize, val: N) {
+        const {
+            assert!(size_of::<N>() < size_of::<T>(), "A lane must be narrower than its register");
         }
+        self.write_lane_raw(index, val);
     }
+
+    /// Put `val` into field `F` with a read-modify-write of the smallest aligned
+    /// lane covering it, keeping other bits untouched. The lane is chosen at compile
+    /// time, falls back to `put_back()` if only the full register covers the field.
+    #[inline]
+    pub fn put_narrow<F: BitField<Reg = R>>(&self, val: T) {
+        let (bytes, index) = const { narrow_lane(size_of::<T>(), F::OFFSET, F::WIDTH) };
+        let val = field_bits::<T, R, F>().val(val);
+        match bytes {
+            1 => self.put_lane_raw::<u8>(index, val),
+            2 => self.put_lane_raw::<u16>(index, val),
+            4 => self.put_lane_raw::<u32>(index, val),
+            _ => self.put_back(val)
+        }
+    }
+
+    /// Put `val` into field `F` with a read-modify-write of the lane of width `N`
+    /// holding it. The field must lie in a single lane:
+    ///
+    /// ```compile_fail
+    /// use re_reg::prelude::*;
+    /// reg_bitfields! { CR(u32) [ DIV [ 12 => 8 ] ] }
+    /// registers_layout! { Regs { ( 0x00 => CR: RW<u32, CR::Reg> ), @END } }
+    /// let io = FakeDevice::<Regs>::new();
+    /// // DIV spans bytes 1 and 2.
+    /// io.CR.put_lane::<u8, CR::DIV>(0x12);
+    /// ```
+    #[inline]
+    pub fn put_lane<N: UIntLike, F: BitField<Reg = R>>(&self, val: T) {
+        let index = const {
+            assert!(size_of::<N>() < size_of::<T>(), "A lane must be narrower than its register");
+            let (first, last) = (F::OFFSET / 8 / size_of::<N>(), (F::OFFSET + F::WIDTH - 1) / 8 / size_of::<N>());
+            assert!(first == last, "The bit field straddles two lanes");
+            first
+        };
+        self.put_lane_raw::<N>(index, field_bits::<T, R, F>().val(val));
+    }
+
+    #[inline]
+    fn put_lane_raw<N: UIntLike>(&self, index: usize, val: MaskedVal<T, R>) {
+        let shift = index * size_of::<N>() * 8;
+        let (mask, val) = (N::from_u64(val.mask.to_u64() >> shift), N::from_u64(val.val.to_u64() >> shift));
+        rmw(|| self.write_lane_raw(index, self.read_lane_raw::<N>(index) & (!mask) | val));
+    }
+}
+
+/// The size and index of the smallest aligned lane of a `size`-byte register
+/// covering bits `offset..offset + width`.
+const fn narrow_lane(size: usize, offset: usize, width: usize) -> (usize, usize) {
+    let (first, last) = (offset / 8, (offset + width - 1) / 8);
+    let mut bytes = 1;
+    while bytes < size {
+        if first / bytes == last / bytes {
+            return (bytes, first / bytes);
+        }
+        bytes *= 2;
+    }
+    (size, 0)
+}
+
+/// The `Bits` of field `F`.
+#[inline]
+fn field_bits<T: UIntLike, R: RegName, F: BitField<Reg = R>>() -> Bits<T, R> {
+    let mask = if F::WIDTH >= 64 { u64::MAX } else { (1 << F::WIDTH) - 1 };
+    Bits::new(F::OFFSET as u8, T::from_u64(mask << F::OFFSET))
 }
@@ -0,0 +1,157 @@
+//! ## crate::register::alias
+//!
+//! This mod contains registers with hardware SET, CLR and TOG aliases, as found on
+//! NXP i.MX and PIC32 peripherals. Writing a mask to an alias sets, clears or flips
+//! those bits of the register in a single atomic write, so `set_back()`, `clear()` and
+//! `toggle()` need no read-modify-write.
+//!
+//! The aliases sit at +0x4, +0x8 and +0xc from the register (or every 8 bytes for
+//! 64-bit registers). Use the `SCT<T, R>` kind in `registers_layout!{}`, which checks
+//! no other register is declared in the alias slots.
+//!
+//! The register doesn't implement `WritableIO`, which would bring in the
+//! read-modify-write `ReadWritableIO` methods. Its writing methods are inherent ones
+//! instead.
+
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+
+use crate::int::UIntLike;
+
+use super::bitfield::{Bits, BitsLike, MaskedVal, ReadableIO};
+use super::endian::{Endianness, NativeEndian};
+use super::fallible::InfallibleIO;
+use super::{InnerRegister, RegName};
+
+/// One alias slot, at least 4 bytes wide.
+#[repr(C, align(4))]
+struct Slot<T>(UnsafeCell<T>);
+
+impl<T: UIntLike> Slot<T> {
+    #[inline]
+    fn write<E: Endianness>(&self, val: T) {
+        unsafe {
+            self.0.get().write_volatile(E::to_hw(val));
+        }
+    }
+}
+
+/// ## Register with SET/CLR/TOG aliases
+///
+/// Another register can't be declared in the alias slots:
+///
+/// ```compile_fail
+/// use re_reg::prelude::*;
+/// registers_layout! {
+///     Regs {
+///         ( 0x00 => CTRL: SCT<u32> ),
+///         ( 0x08 => STAT: RO<u32> ),
+///         @END
+///     }
+/// }
+/// ```
+#[repr(C)]
+pub struct AliasedInnerRegister<T, R = (), E = NativeEndian>
+where
+    T: UIntLike,
+    R: RegName + BitsLike<T>,
+    E: Endianness
+{
+    raw: Slot<T>,
+    set: Slot<T>,
+    clr: Slot<T>,
+    tog: Slot<T>,
+    _reg: PhantomData<(R, E)>
+}
+
+impl<T, R, E> InnerRegister for AliasedInnerRegister<T, R, E>
+where
+    T: UIntLike,
+    R: RegName + BitsLike<T>,
+    E: Endianness
+{
+    type Int = T;
+    type Name = R;
+    type Endian = E;
+}
+
+impl<T, R, E> InfallibleIO for AliasedInnerRegister<T, R, E>
+where
+    T: UIntLike,
+    R: RegName + BitsLike<T>,
+    E: Endianness
+{}
+
+impl<T, R, E> ReadableIO<T, R> for AliasedInnerRegister<T, R, E>
+where
+    T: UIntLike,
+    R: RegName + BitsLike<T>,
+    E: Endianness
+{
+    #[inline]
+    fn read(&self) -> T {
+        let val = E::from_hw(unsafe {
+            self.raw.0.get().read_volatile()
+        });
+        trace!(Read, self, val);
+        val
+    }
+}
+
+impl<T, R, E> AliasedInnerRegister<T, R, E>
+where
+    T: UIntLike,
+    R: RegName + BitsLike<T>,
+    E: Endianness
+{
+    #[inline]
+    fn write_alias(&self, alias: &Slot<T>, val: T) {
+        trace!(Write, self, at alias as *const _ as usize - self as *const _ as usize, val);
+        alias.write::<E>(val);
+    }
+
+    /// Write a value to the register.
+    #[inline]
+    pub fn write(&self, val: T) {
+        trace!(Write, self, val);
+        self.raw.write::<E>(val);
+    }
+
+    /// Put a value into target bits, other bits become 0.
+    #[inline]
+    pub fn put(&self, val: MaskedVal<T, R>) {
+        self.write(val.val);
+    }
+
+    /// Set target bits to 1, other bits become 0.
+    #[inline]
+    pub fn set(&self, bits: Bits<T, R>) {
+        self.write(bits.mask);
+    }
+
+    /// Put the value into target bits, while keeping others untouched. This writes
+    /// the CLR alias then the SET alias, so the field goes through 0 bits in between.
+    #[inline]
+    pub fn put_back(&self, val: MaskedVal<T, R>) {
+        self.write_alias(&self.clr, val.mask & (!val.val));
+        self.write_alias(&self.set, val.val);
+    }
+
+    /// Set target bits with a single write to the SET alias.
+    #[inline]
+    pub fn set_back(&self, bits: Bits<T, R>) {
+        self.write_alias(&self.set, bits.mask);
+    }
+
+    /// Set target bits to 0 with a single write to the CLR alias.
+    #[inline]
+    pub fn clear(&self, bits: Bits<T, R>) {
+        self.write_alias(&self.clr, bits.mask);
+    }
+
+    /// Flip target bits with a single write to the TOG alias.
+    #[inline]
+    pub fn toggle(&self, bits: Bits<T, R>) {
+        self.write_alias(&self.tog, bits.mask);
+    }
+}
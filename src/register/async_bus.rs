@@ -0,0 +1,276 @@
+//! ## crate::register::async_bus
+//!
+//! This mod contains the async counterparts of the bus utilities in `register::bus`,
+//! for drivers running on an async executor. Users implement `AsyncRegisterBus` for
+//! their bus driver and access registers of a `bus_registers!{}` map through
+//! `AsyncBusDevice`.
+//!
+//! Errors of async bus registers carry the bus error in `Error::Bus`, like the sync
+//! ones. The IO traits here take `&mut self`, so a device can't have two transfers in
+//! flight at once, and a read-modify-write can't be interleaved with other accesses
+//! to the same device.
+
+use core::future::Future;
+use core::marker::PhantomData;
+use core::mem::size_of;
+
+use crate::error::Error;
+use crate::int::UIntLike;
+
+use super::backend::RegDesc;
+use super::bitfield::{Bits, BitsLike, LocalCopy, MaskedVal};
+use super::bus::{addr_fits, from_bytes, to_bytes, RegisterMap};
+use super::endian::Endianness;
+use super::{InnerRegister, RCInnerRegister, Readable, RegName, Writable};
+
+/// ## Async register bus
+///
+/// The async version of `RegisterBus`, see it for details.
+pub trait AsyncRegisterBus {
+    type Error;
+    type Addr: UIntLike;
+
+    const AUTO_INCREMENT: bool = true;
+    const BIG_ENDIAN: bool = true;
+
+    /// Read `buf.len()` bytes starting from register `addr`.
+    fn read_regs(&mut self, addr: Self::Addr, buf: &mut [u8]) -> impl Future<Output = Result<(), Self::Error>>;
+
+    /// Write `data` starting from register `addr`.
+    fn write_regs(&mut self, addr: Self::Addr, data: &[u8]) -> impl Future<Output = Result<(), Self::Error>>;
+}
+
+/// Shared by the async IO traits, so they agree on the error type.
+pub trait AsyncIO {
+    type Error;
+}
+
+/// ## Async Readable IO trait
+pub trait AsyncReadableIO<T: UIntLike, R: RegName = ()>: AsyncIO {
+    fn read(&mut self) -> impl Future<Output = Result<T, Self::Error>>;
+
+    /// Get the value in the target field. Don't use combined `Bits` here.
+    #[inline]
+    fn get(&mut self, bits: Bits<T, R>) -> impl Future<Output = Result<T, Self::Error>> {
+        async move {
+            Ok((self.read().await? & bits.mask) >> (bits.offset as usize))
+        }
+    }
+
+    /// Check if target bits are set.
+    #[inline]
+    fn is_set(&mut self, bits: Bits<T, R>) -> impl Future<Output = Result<bool, Self::Error>> {
+        async move {
+            Ok((self.read().await? & bits.mask) == bits.mask)
+        }
+    }
+}
+
+/// ## Async Writable IO trait
+///
+/// Methods here will overwrite non-target bits' values to 0.
+pub trait AsyncWritableIO<T: UIntLike, R: RegName = ()>: AsyncIO {
+    fn write(&mut self, val: T) -> impl Future<Output = Result<(), Self::Error>>;
+
+    /// Put a value into target bits, other bits become 0.
+    #[inline]
+    fn put(&mut self, val: MaskedVal<T, R>) -> impl Future<Output = Result<(), Self::Error>> {
+        self.write(val.val)
+    }
+
+    /// Set target bits to 1, other bits become 0.
+    #[inline]
+    fn set(&mut self, bits: Bits<T, R>) -> impl Future<Output = Result<(), Self::Error>> {
+        self.write(bits.mask)
+    }
+
+    /// Set all bits to 1.
+    #[inline]
+    fn set_all(&mut self) -> impl Future<Output = Result<(), Self::Error>> {
+        self.write(T::all())
+    }
+
+    /// Set all bits to 0.
+    #[inline]
+    fn clear_all(&mut self) -> impl Future<Output = Result<(), Self::Error>> {
+        self.write(T::zero())
+    }
+}
+
+/// ## Async Read-Writable IO trait
+///
+/// Read-modify-write operations, keeping the other bits' value.
+pub trait AsyncReadWritableIO<T: UIntLike, R: RegName = ()>: AsyncIO {
+    fn put_back(&mut self, val: MaskedVal<T, R>) -> impl Future<Output = Result<(), Self::Error>>;
+    fn set_back(&mut self, bits: Bits<T, R>) -> impl Future<Output = Result<(), Self::Error>>;
+    fn clear(&mut self, bits: Bits<T, R>) -> impl Future<Output = Result<(), Self::Error>>;
+
+    /// Read the register, then put the value returned by `f` into target bits.
+    fn modify<F>(&mut self, f: F) -> impl Future<Output = Result<(), Self::Error>>
+    where
+        F: FnOnce(LocalCopy<T, R>) -> MaskedVal<T, R>;
+}
+
+impl<U, T: UIntLike, R: RegName> AsyncReadWritableIO<T, R> for U
+where
+    U: AsyncReadableIO<T, R> + AsyncWritableIO<T, R>
+{
+    /// Put the value into target bits, while keeping others untouched.
+    #[inline]
+    async fn put_back(&mut self, val: MaskedVal<T, R>) -> Result<(), Self::Error> {
+        let cur = self.read().await?;
+        self.write(cur & (!val.mask) | val.val).await
+    }
+
+    /// Set target bits, while keeping others untouched.
+    #[inline]
+    async fn set_back(&mut self, bits: Bits<T, R>) -> Result<(), Self::Error> {
+        let cur = self.read().await?;
+        self.write(cur | bits.mask).await
+    }
+
+    /// Set target bits to 0, while keeping others untouched.
+    #[inline]
+    async fn clear(&mut self, bits: Bits<T, R>) -> Result<(), Self::Error> {
+        let cur = self.read().await?;
+        self.write(cur & (!bits.mask)).await
+    }
+
+    #[inline]
+    async fn modify<F>(&mut self, f: F) -> Result<(), Self::Error>
+    where
+        F: FnOnce(LocalCopy<T, R>) -> MaskedVal<T, R>
+    {
+        let cur = self.read().await?;
+        let val = f(LocalCopy::new(cur));
+        self.write(cur & (!val.mask) | val.val).await
+    }
+}
+
+/// ## Async bus device
+///
+/// A register map `L` generated by `bus_registers!{}`, instantiated over async bus `B`.
+pub struct AsyncBusDevice<B: AsyncRegisterBus, L> {
+    bus: B,
+    _layout: PhantomData<fn() -> L>
+}
+
+impl<B: AsyncRegisterBus, L> AsyncBusDevice<B, L> {
+    pub const fn new(bus: B) -> Self {
+        Self {
+            bus,
+            _layout: PhantomData
+        }
+    }
+
+    /// Get the bus back.
+    pub fn release(self) -> B {
+        self.bus
+    }
+
+    #[inline]
+    pub fn bus_mut(&mut self) -> &mut B {
+        &mut self.bus
+    }
+
+    /// Get the register described by `desc`.
+    #[inline]
+    pub fn reg<I: InnerRegister>(&mut self, desc: RegDesc<L, I>) -> AsyncBusRegister<'_, B, I>
+    where
+        L: RegisterMap
+    {
+        const { assert!(addr_fits::<B::Addr>(L::END), "Register map addresses don't fit AsyncRegisterBus::Addr") };
+        AsyncBusRegister {
+            bus: &mut self.bus,
+            addr: desc.offset(),
+            _reg: PhantomData
+        }
+    }
+}
+
+/// ## Async bus register
+///
+/// A register behind an async bus. The available operations depend on the register
+/// kind `I`.
+pub struct AsyncBusRegister<'a, B: AsyncRegisterBus, I: InnerRegister> {
+    bus: &'a mut B,
+    addr: usize,
+    _reg: PhantomData<fn() -> I>
+}
+
+impl<B: AsyncRegisterBus, I: InnerRegister> AsyncBusRegister<'_, B, I> {
+    #[inline]
+    pub fn addr(&self) -> B::Addr {
+        B::Addr::from_u64(self.addr as u64)
+    }
+
+    async fn read_raw(&mut self) -> Result<I::Int, B::Error> {
+        let mut buf = [0u8; 8];
+        let buf = &mut buf[..size_of::<I::Int>()];
+        if B::AUTO_INCREMENT {
+            self.bus.read_regs(self.addr(), buf).await?;
+        } else {
+            for (i, byte) in buf.iter_mut().enumerate() {
+                let addr = B::Addr::from_u64((self.addr + i) as u64);
+                self.bus.read_regs(addr, core::slice::from_mut(byte)).await?;
+            }
+        }
+        Ok(from_bytes::<I::Int>(buf, B::BIG_ENDIAN))
+    }
+
+    async fn write_raw(&mut self, val: I::Int) -> Result<(), B::Error> {
+        let mut buf = [0u8; 8];
+        let buf = &mut buf[..size_of::<I::Int>()];
+        to_bytes(val, buf, B::BIG_ENDIAN);
+        if B::AUTO_INCREMENT {
+            self.bus.write_regs(self.addr(), buf).await
+        } else {
+            for (i, byte) in buf.iter().enumerate() {
+                let addr = B::Addr::from_u64((self.addr + i) as u64);
+                self.bus.write_regs(addr, core::slice::from_ref(byte)).await?;
+            }
+            Ok(())
+        }
+    }
+}
+
+impl<B: AsyncRegisterBus, I: InnerRegister> AsyncIO for AsyncBusRegister<'_, B, I> {
+    type Error = Error<B::Error>;
+}
+
+impl<B, I> AsyncReadableIO<I::Int, I::Name> for AsyncBusRegister<'_, B, I>
+where
+    B: AsyncRegisterBus,
+    I: InnerRegister + Readable
+{
+    #[inline]
+    async fn read(&mut self) -> Result<I::Int, Error<B::Error>> {
+        self.read_raw().await.map_err(Error::Bus)
+    }
+}
+
+impl<B, I> AsyncWritableIO<I::Int, I::Name> for AsyncBusRegister<'_, B, I>
+where
+    B: AsyncRegisterBus,
+    I: InnerRegister + Writable
+{
+    #[inline]
+    async fn write(&mut self, val: I::Int) -> Result<(), Error<B::Error>> {
+        self.write_raw(val).await.map_err(Error::Bus)
+    }
+}
+
+impl<B, T, R, E> AsyncBusRegister<'_, B, RCInnerRegister<T, R, E>>
+where
+    B: AsyncRegisterBus,
+    T: UIntLike,
+    R: RegName + BitsLike<T>,
+    E: Endianness
+{
+    /// Read the register once, see `RCInnerRegister::take()`. The bus decides the
+    /// byte order, `E` is ignored.
+    #[inline]
+    pub async fn take(&mut self) -> Result<LocalCopy<T, R>, Error<B::Error>> {
+        self.read_raw().await.map(LocalCopy::new).map_err(Error::Bus)
+    }
+}
@@ -0,0 +1,188 @@
+//! ## crate::register::atomic
+//!
+//! This mod contains registers in memory shared between cores or processes, like
+//! mailboxes. `AtomicInnerRegister` is backed by `core::sync::atomic` integers, so its
+//! read-modify-write methods are atomic:
+//! - `set_back()` is a `fetch_or()`.
+//! - `clear()` is a `fetch_and()`.
+//! - `toggle()` is a `fetch_xor()`.
+//! - `put_back()` is a compare-and-swap loop.
+//!
+//! Reads are acquire loads, writes are release stores and read-modify-writes are
+//! both. Use the `ATOMIC<T, R>` kind in `registers_layout!{}`.
+//!
+//! The register doesn't implement `WritableIO`, which would bring in the non-atomic
+//! `ReadWritableIO` methods. Its writing methods are inherent ones instead.
+
+use core::marker::PhantomData;
+use core::sync::atomic::Ordering;
+
+use crate::int::UIntLike;
+
+use super::bitfield::{Bits, BitsLike, MaskedVal, ReadableIO};
+use super::endian::NativeEndian;
+use super::fallible::InfallibleIO;
+use super::{InnerRegister, RegName};
+
+/// Integers with an atomic counterpart.
+pub trait AtomicUInt: UIntLike {
+    type Atomic;
+
+    fn load(atomic: &Self::Atomic, order: Ordering) -> Self;
+    fn store(atomic: &Self::Atomic, val: Self, order: Ordering);
+    fn fetch_or(atomic: &Self::Atomic, val: Self, order: Ordering) -> Self;
+    fn fetch_and(atomic: &Self::Atomic, val: Self, order: Ordering) -> Self;
+    fn fetch_xor(atomic: &Self::Atomic, val: Self, order: Ordering) -> Self;
+    fn compare_exchange_weak(
+        atomic: &Self::Atomic,
+        cur: Self,
+        new: Self,
+        success: Ordering,
+        failure: Ordering
+    ) -> Result<Self, Self>;
+}
+
+macro_rules! impl_atomic_uint {
+    ($typ:ty, $atomic:ty) => {
+        impl AtomicUInt for $typ {
+            type Atomic = $atomic;
+
+            #[inline]
+            fn load(atomic: &$atomic, order: Ordering) -> Self {
+                atomic.load(order)
+            }
+            #[inline]
+            fn store(atomic: &$atomic, val: Self, order: Ordering) {
+                atomic.store(val, order);
+            }
+            #[inline]
+            fn fetch_or(atomic: &$atomic, val: Self, order: Ordering) -> Self {
+                atomic.fetch_or(val, order)
+            }
+            #[inline]
+            fn fetch_and(atomic: &$atomic, val: Self, order: Ordering) -> Self {
+                atomic.fetch_and(val, order)
+            }
+            #[inline]
+            fn fetch_xor(atomic: &$atomic, val: Self, order: Ordering) -> Self {
+                atomic.fetch_xor(val, order)
+            }
+            #[inline]
+            fn compare_exchange_weak(
+                atomic: &$atomic,
+                cur: Self,
+                new: Self,
+                success: Ordering,
+                failure: Ordering
+            ) -> Result<Self, Self> {
+                atomic.compare_exchange_weak(cur, new, success, failure)
+            }
+        }
+    };
+}
+
+#[cfg(target_has_atomic = "8")]
+impl_atomic_uint!(u8, core::sync::atomic::AtomicU8);
+#[cfg(target_has_atomic = "16")]
+impl_atomic_uint!(u16, core::sync::atomic::AtomicU16);
+#[cfg(target_has_atomic = "32")]
+impl_atomic_uint!(u32, core::sync::atomic::AtomicU32);
+#[cfg(target_has_atomic = "64")]
+impl_atomic_uint!(u64, core::sync::atomic::AtomicU64);
+
+/// ## Atomic register
+#[repr(transparent)]
+pub struct AtomicInnerRegister<T, R = ()>
+where
+    T: AtomicUInt,
+    R: RegName + BitsLike<T>
+{
+    raw: T::Atomic,
+    _reg: PhantomData<R>
+}
+
+impl<T, R> InnerRegister for AtomicInnerRegister<T, R>
+where
+    T: AtomicUInt,
+    R: RegName + BitsLike<T>
+{
+    type Int = T;
+    type Name = R;
+    type Endian = NativeEndian;
+}
+
+impl<T, R> InfallibleIO for AtomicInnerRegister<T, R>
+where
+    T: AtomicUInt,
+    R: RegName + BitsLike<T>
+{}
+
+impl<T, R> ReadableIO<T, R> for AtomicInnerRegister<T, R>
+where
+    T: AtomicUInt,
+    R: RegName + BitsLike<T>
+{
+    #[inline]
+    fn read(&self) -> T {
+        T::load(&self.raw, Ordering::Acquire)
+    }
+}
+
+impl<T, R> AtomicInnerRegister<T, R>
+where
+    T: AtomicUInt,
+    R: RegName + BitsLike<T>
+{
+    /// Write a value to the register.
+    #[inline]
+    pub fn write(&self, val: T) {
+        T::store(&self.raw, val, Ordering::Release);
+    }
+
+    /// Put a value into target bits, other bits become 0.
+    #[inline]
+    pub fn put(&self, val: MaskedVal<T, R>) {
+        self.write(val.val);
+    }
+
+    /// Set target bits to 1, other bits become 0.
+    #[inline]
+    pub fn set(&self, bits: Bits<T, R>) {
+        self.write(bits.mask);
+    }
+
+    /// Atomically put the value into target bits, while keeping others untouched.
+    /// Returns the previous value.
+    #[inline]
+    pub fn put_back(&self, val: MaskedVal<T, R>) -> T {
+        let mut cur = T::load(&self.raw, Ordering::Relaxed);
+        loop {
+            let new = cur & (!val.mask) | val.val;
+            match T::compare_exchange_weak(&self.raw, cur, new, Ordering::AcqRel, Ordering::Relaxed) {
+                Ok(prev) => return prev,
+                Err(actual) => cur = actual
+            }
+        }
+    }
+
+    /// Atomically set target bits, while keeping others untouched. Returns the
+    /// previous value.
+    #[inline]
+    pub fn set_back(&self, bits: Bits<T, R>) -> T {
+        T::fetch_or(&self.raw, bits.mask, Ordering::AcqRel)
+    }
+
+    /// Atomically set target bits to 0, while keeping others untouched. Returns the
+    /// previous value.
+    #[inline]
+    pub fn clear(&self, bits: Bits<T, R>) -> T {
+        T::fetch_and(&self.raw, !bits.mask, Ordering::AcqRel)
+    }
+
+    /// Atomically flip target bits, while keeping others untouched. Returns the
+    /// previous value.
+    #[inline]
+    pub fn toggle(&self, bits: Bits<T, R>) -> T {
+        T::fetch_xor(&self.raw, bits.mask, Ordering::AcqRel)
+    }
+}
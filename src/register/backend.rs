@@ -0,0 +1,257 @@
+//! ## crate::register::backend
+//!
+//! This mod contains the register access backend abstraction. A `RegisterBackend`
+//! reads and writes a register of width `T` at a given offset, so the same layouts
+//! and bit fields can drive memory-mapped IO, in-memory fakes or bus-attached devices.
+//!
+//! `registers_layout!{}` generates a `RegDesc` constant for each register, e.g.
+//! `MyDeviceRegs::CR`. Wrap a backend with `Device` and pass the constant to
+//! `Device::reg()` to get a register implementing the usual IO traits.
+
+use core::marker::PhantomData;
+use core::mem::size_of;
+
+use crate::int::UIntLike;
+
+use super::bitfield::{BitsLike, LocalCopy, ReadableIO, WritableIO};
+use super::fallible::InfallibleIO;
+use super::split::{half_slot, read_split, write_split, SplitInnerRegister, SplitOrder};
+use super::endian::Endianness;
+use super::{InnerRegister, RCInnerRegister, Readable, RegName, ShadowTarget, Shadowed, Writable, WOSInnerRegister};
+
+/// ## Register access backend
+///
+/// The `offset` is relative to the beginning of the layout.
+pub trait RegisterBackend {
+    fn read<T: UIntLike>(&self, offset: usize) -> T;
+    fn write<T: UIntLike>(&self, offset: usize, val: T);
+}
+
+/// ## Memory-mapped IO backend
+///
+/// Accesses registers with volatile reads and writes at `base + offset`.
+#[derive(Clone, Copy)]
+pub struct Mmio {
+    base: usize
+}
+
+impl Mmio {
+    /// # Safety
+    ///
+    /// `base` must be the address of a register block matching the layouts used with
+    /// this backend, and it must stay valid as long as the backend is used.
+    pub const unsafe fn new(base: usize) -> Self {
+        Self { base }
+    }
+
+    #[inline]
+    pub fn base(&self) -> usize {
+        self.base
+    }
+}
+
+impl RegisterBackend for Mmio {
+    #[inline]
+    fn read<T: UIntLike>(&self, offset: usize) -> T {
+        unsafe {
+            ((self.base + offset) as *const T).read_volatile()
+        }
+    }
+
+    #[inline]
+    fn write<T: UIntLike>(&self, offset: usize, val: T) {
+        unsafe {
+            ((self.base + offset) as *mut T).write_volatile(val);
+        }
+    }
+}
+
+/// ## Register descriptor
+///
+/// The offset of register `I` in layout `L`. These are generated by
+/// `registers_layout!{}` as associated constants of the layout. For register maps
+/// generated by `bus_registers!{}`, the offset is the register's bus address.
+pub struct RegDesc<L, I> {
+    offset: usize,
+    _reg: PhantomData<fn() -> (L, I)>
+}
+
+impl<L, I> Clone for RegDesc<L, I> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<L, I> Copy for RegDesc<L, I> {}
+
+impl<L, I> RegDesc<L, I> {
+    /// The descriptor of register `I` at `offset` of layout `L`.
+    ///
+    /// # Safety
+    ///
+    /// `L` must hold a register of kind `I` at `offset`. Devices access
+    /// `base + offset` without further checks.
+    pub const unsafe fn new(offset: usize) -> Self {
+        Self {
+            offset,
+            _reg: PhantomData
+        }
+    }
+
+    #[inline]
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+/// ## Device
+///
+/// A layout `L` instantiated over a backend `B`.
+pub struct Device<B: RegisterBackend, L> {
+    backend: B,
+    _layout: PhantomData<fn() -> L>
+}
+
+impl<B: RegisterBackend, L> Device<B, L> {
+    pub const fn new(backend: B) -> Self {
+        Self {
+            backend,
+            _layout: PhantomData
+        }
+    }
+
+    #[inline]
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// Get the register described by `desc`.
+    #[inline]
+    pub fn reg<I: InnerRegister>(&self, desc: RegDesc<L, I>) -> BackedRegister<'_, B, I> {
+        BackedRegister {
+            backend: &self.backend,
+            offset: desc.offset,
+            _reg: PhantomData
+        }
+    }
+}
+
+/// ## Backed register
+///
+/// A register accessed through a backend. It implements the same IO traits as the
+/// `InnerRegister` kind `I` it stands for, and converts values to `I`'s byte order.
+pub struct BackedRegister<'a, B: RegisterBackend, I: InnerRegister> {
+    backend: &'a B,
+    offset: usize,
+    _reg: PhantomData<fn() -> I>
+}
+
+impl<B: RegisterBackend, I: InnerRegister> BackedRegister<'_, B, I> {
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<B: RegisterBackend, I: InnerRegister> InfallibleIO for BackedRegister<'_, B, I> {}
+
+impl<B, I> ReadableIO<I::Int, I::Name> for BackedRegister<'_, B, I>
+where
+    B: RegisterBackend,
+    I: InnerRegister + Readable
+{
+    #[inline]
+    fn read(&self) -> I::Int {
+        I::Endian::from_hw(self.backend.read(self.offset))
+    }
+}
+
+impl<B, I> WritableIO<I::Int, I::Name> for BackedRegister<'_, B, I>
+where
+    B: RegisterBackend,
+    I: InnerRegister + Writable
+{
+    #[inline]
+    fn write(&self, val: I::Int) {
+        self.backend.write(self.offset, I::Endian::to_hw(val));
+    }
+}
+
+impl<B, T, R, E> BackedRegister<'_, B, RCInnerRegister<T, R, E>>
+where
+    B: RegisterBackend,
+    T: UIntLike,
+    R: RegName + BitsLike<T>,
+    E: Endianness
+{
+    /// Read the register once, see `RCInnerRegister::take()`.
+    #[inline]
+    #[must_use = "the read has side effects, the value can't be read again"]
+    pub fn take(&self) -> LocalCopy<T, R> {
+        LocalCopy::new(E::from_hw(self.backend.read(self.offset)))
+    }
+}
+
+impl<'a, B, T, R, E> BackedRegister<'a, B, WOSInnerRegister<T, R, E>>
+where
+    B: RegisterBackend,
+    T: UIntLike,
+    R: RegName + BitsLike<T>,
+    E: Endianness
+{
+    /// Create the shadowed handle of this register, see `WOSInnerRegister::shadowed()`.
+    #[inline]
+    pub fn shadowed(self, init: T) -> Shadowed<Self> {
+        Shadowed::new(self, init)
+    }
+}
+
+impl<B, T, R, E> ShadowTarget for BackedRegister<'_, B, WOSInnerRegister<T, R, E>>
+where
+    B: RegisterBackend,
+    T: UIntLike,
+    R: RegName + BitsLike<T>,
+    E: Endianness
+{
+    type Int = T;
+    type Name = R;
+
+    #[inline]
+    fn write_raw(&self, val: T) {
+        self.backend.write(self.offset, E::to_hw(val));
+    }
+}
+
+impl<B, T, H, O, R, E> ReadableIO<T, R> for BackedRegister<'_, B, SplitInnerRegister<T, H, O, R, E>>
+where
+    B: RegisterBackend,
+    T: UIntLike,
+    H: UIntLike,
+    O: SplitOrder,
+    R: RegName + BitsLike<T>,
+    E: Endianness
+{
+    #[inline]
+    fn read(&self) -> T {
+        read_split::<T, H, O>(|half| {
+            E::from_hw(self.backend.read(self.offset + half_slot::<E>(half) * size_of::<H>()))
+        })
+    }
+}
+
+impl<B, T, H, O, R, E> WritableIO<T, R> for BackedRegister<'_, B, SplitInnerRegister<T, H, O, R, E>>
+where
+    B: RegisterBackend,
+    T: UIntLike,
+    H: UIntLike,
+    O: SplitOrder,
+    R: RegName + BitsLike<T>,
+    E: Endianness
+{
+    #[inline]
+    fn write(&self, val: T) {
+        write_split::<T, H, O>(val, |half, hval| {
+            self.backend.write(self.offset + half_slot::<E>(half) * size_of::<H>(), E::to_hw(hval));
+        });
+    }
+}
@@ -0,0 +1,132 @@
+//! ## crate::register::bank
+//!
+//! This mod contains bank-selected register views, for peripherals reusing the
+//! same offsets for different registers depending on a BANK field in a control
+//! register.
+//!
+//! Each bank is a layout of its own, overlaid on the owner layout's base. The banks
+//! are declared in the owner's `registers_layout!{}`, and `owner.bank::<BankN>()`
+//! selects one and returns a guard exposing its registers.
+//!
+//! The guard borrows the owner mutably, so only one bank is selected at a time and
+//! the registers of another bank can't be reached while it is. Registers outside the
+//! bank are still reachable through `BankGuard::owner()`.
+//!
+//! A bank layout must be a `registers_layout!{}` layout, no bigger and no more
+//! aligned than its owner:
+//!
+//! ```compile_fail
+//! use re_reg::prelude::*;
+//! reg_bitfields! {
+//!     CTRL(u32) [ BANK [ 4 => 2 ] ]
+//! }
+//! registers_layout! {
+//!     DevRegs {
+//!         ( 0x00 => CTRL: RW<u32, CTRL::Reg> ),
+//!         ( 0x04 => DATA: RW<u32> ),
+//!         @END
+//!         @BANKS CTRL[CTRL::BANK] {
+//!             // `String` isn't a register layout.
+//!             Evil = 1 => String
+//!         }
+//!     }
+//! }
+//! ```
+
+use core::marker::PhantomData;
+use core::ops::Deref;
+
+use crate::int::UIntLike;
+
+use super::{RegName, RegisterLayout};
+use super::bitfield::{Bits, ReadableIO, WritableIO};
+use super::lock::rmw;
+
+/// ## Register bank
+///
+/// Implemented by the bank markers generated by `registers_layout!{}`.
+///
+/// # Safety
+///
+/// `Layout` must be no bigger and no more aligned than `Owner`, as the guard
+/// overlays it on the owner.
+pub unsafe trait Bank {
+    /// The layout holding the bank-select register.
+    type Owner: RegisterLayout;
+    /// The registers visible while this bank is selected.
+    type Layout: RegisterLayout;
+
+    /// Select this bank, returns the previously selected bank's selector value.
+    fn select(owner: &Self::Owner) -> u64;
+
+    /// Select the bank `prev` again.
+    fn restore(owner: &Self::Owner, prev: u64);
+}
+
+/// Put `val` into the selector field `bits` of `reg` with a single read and a single
+/// write, returns the field's previous value.
+#[inline]
+pub fn select_field<U, T, R>(reg: &U, bits: Bits<T, R>, val: T) -> T
+where
+    U: ReadableIO<T, R> + WritableIO<T, R>,
+    T: UIntLike,
+    R: RegName
+{
+    let cur = rmw(|| {
+        let cur = reg.read();
+        reg.write(cur & (!bits.mask) | bits.val(val).val);
+        cur
+    });
+    (cur & bits.mask) >> (bits.offset as usize)
+}
+
+/// Adds `bank()` to layouts declaring banks in `registers_layout!{}`.
+pub trait BankSelect: Sized {
+    /// Select bank `B` and return a guard exposing its registers. The previous bank
+    /// is selected again when the guard drops, a forgotten guard leaves `B` selected.
+    #[inline]
+    fn bank<B: Bank<Owner = Self>>(&mut self) -> BankGuard<'_, B> {
+        BankGuard {
+            prev: B::select(self),
+            owner: self,
+            _bank: PhantomData
+        }
+    }
+}
+
+/// ## Bank guard
+///
+/// Dereferences to the selected bank's layout.
+pub struct BankGuard<'a, B: Bank> {
+    owner: &'a mut B::Owner,
+    prev: u64,
+    _bank: PhantomData<B>
+}
+
+impl<B: Bank> BankGuard<'_, B> {
+    /// The owner layout, for the registers outside the bank.
+    #[inline]
+    pub fn owner(&self) -> &B::Owner {
+        self.owner
+    }
+}
+
+impl<B: Bank> Deref for BankGuard<'_, B> {
+    type Target = B::Layout;
+
+    #[inline]
+    fn deref(&self) -> &B::Layout {
+        // SAFETY: the bank layout overlays the owner layout, it is no bigger and no
+        // more aligned than the owner, see `Bank`.
+        unsafe {
+            &*(&*self.owner as *const B::Owner as *const B::Layout)
+        }
+    }
+}
+
+impl<B: Bank> Drop for BankGuard<'_, B> {
+    #[inline]
+    fn drop(&mut self) {
+        B::restore(self.owner, self.prev);
+    }
+}
@@ -0,0 +1,92 @@
+//! ## crate::register::barrier
+//!
+//! This mod contains memory barriers around register accesses.
+//!
+//! Register reads and writes are single volatile accesses. The compiler keeps them
+//! in program order relative to each other, but neither the compiler nor the CPU
+//! orders them against plain memory accesses. On weakly ordered targets a register
+//! write kicking off a DMA may thus reach the device before the DMA buffer stores,
+//! and a status read may be passed by the following buffer loads.
+//!
+//! There are two ways to order them:
+//! - Per call, `WritableIO::write_release::<B>()` issues a release barrier before
+//!   the write, and `ReadableIO::read_acquire::<B>()` an acquire barrier after the
+//!   read.
+//! - Per device, wrap the backend of a `Device` with `Fenced`, so every access of
+//!   every register in the layout is ordered.
+//!
+//! Both use a `Barrier`, which defaults to the `core::sync::atomic` fences. These
+//! order memory as seen by other CPUs, which may not cover a device on some targets
+//! (e.g. Arm needs `dmb sy` or `dsb` rather than `dmb ish`). Implement `Barrier`
+//! with the target's instructions there.
+
+use core::marker::PhantomData;
+use core::sync::atomic::{fence, Ordering};
+
+use crate::int::UIntLike;
+
+use super::backend::RegisterBackend;
+
+/// ## Memory barrier
+pub trait Barrier {
+    /// Memory accesses before the barrier complete before any access after it.
+    #[inline]
+    fn fence() {
+        fence(Ordering::SeqCst);
+    }
+
+    /// Memory accesses before the barrier complete before any write after it.
+    #[inline]
+    fn release() {
+        fence(Ordering::Release);
+    }
+
+    /// Reads before the barrier complete before any memory access after it.
+    #[inline]
+    fn acquire() {
+        fence(Ordering::Acquire);
+    }
+}
+
+/// The `core::sync::atomic` fences.
+pub struct AtomicBarrier;
+
+impl Barrier for AtomicBarrier {}
+
+/// ## Fenced backend
+///
+/// Wraps backend `B`, issuing a release barrier before every write and an acquire
+/// barrier after every read.
+pub struct Fenced<B: RegisterBackend, F: Barrier = AtomicBarrier> {
+    backend: B,
+    _barrier: PhantomData<F>
+}
+
+impl<B: RegisterBackend, F: Barrier> Fenced<B, F> {
+    pub const fn new(backend: B) -> Self {
+        Self {
+            backend,
+            _barrier: PhantomData
+        }
+    }
+
+    #[inline]
+    pub fn inner(&self) -> &B {
+        &self.backend
+    }
+}
+
+impl<B: RegisterBackend, F: Barrier> RegisterBackend for Fenced<B, F> {
+    #[inline]
+    fn read<T: UIntLike>(&self, offset: usize) -> T {
+        let val = self.backend.read(offset);
+        F::acquire();
+        val
+    }
+
+    #[inline]
+    fn write<T: UIntLike>(&self, offset: usize, val: T) {
+        F::release();
+        self.backend.write(offset, val);
+    }
+}
@@ -0,0 +1,139 @@
+//! ## crate::register::bitband
+//!
+//! This mod contains Cortex-M3/M4 bit-banding. A bit-band region maps every bit of
+//! a memory region to a word of its alias region, so writing 1 or 0 to the word sets
+//! or clears the bit in a single atomic write.
+//!
+//! `BitBand` describes a region, and computes the alias address of a 1-bit field
+//! of a register in a layout overlaid on it. The standard regions are
+//! `BitBand::sram()` and `BitBand::peripheral()`, other ones (e.g. an in-memory
+//! model in tests) are created with `BitBand::new()`. All of them are unsafe to
+//! create, since the accesses go to the alias addresses.
+//!
+//! Fields are given by their `BitField` type, so a field wider than one bit fails
+//! to compile:
+//!
+//! ```compile_fail
+//! use re_reg::prelude::*;
+//! reg_bitfields! { ODR(u32) [ PORT [ 0 => 16 ] ] }
+//! registers_layout! { GpioRegs { ( 0x00 => ODR: RW<u32, ODR::Reg> ), @END } }
+//! let io = unsafe { &*(0x4001_0800 as *const GpioRegs) };
+//! unsafe { BitBand::peripheral() }.set_bit_atomic::<_, ODR::PORT>(&io.ODR);
+//! ```
+
+use core::mem::size_of;
+
+use super::InnerRegister;
+use super::bitfield::BitField;
+use super::endian::Endianness;
+
+/// ## Bit-band region
+#[derive(Clone, Copy)]
+pub struct BitBand {
+    base: usize,
+    alias: usize,
+    size: usize
+}
+
+impl BitBand {
+    /// The SRAM bit-band region, 1MB from 0x2000_0000.
+    ///
+    /// # Safety
+    ///
+    /// The target must be a Cortex-M3/M4 with the SRAM bit-band region mapped.
+    pub const unsafe fn sram() -> Self {
+        Self { base: 0x2000_0000, alias: 0x2200_0000, size: 0x10_0000 }
+    }
+
+    /// The peripheral bit-band region, 1MB from 0x4000_0000.
+    ///
+    /// # Safety
+    ///
+    /// The target must be a Cortex-M3/M4 with the peripheral bit-band region mapped.
+    pub const unsafe fn peripheral() -> Self {
+        Self { base: 0x4000_0000, alias: 0x4200_0000, size: 0x10_0000 }
+    }
+
+    /// # Safety
+    ///
+    /// The `size` bytes from `base` must be bit-banded to the words from `alias`, and
+    /// both must stay valid as long as the region is used.
+    pub const unsafe fn new(base: usize, alias: usize, size: usize) -> Self {
+        Self { base, alias, size }
+    }
+
+    /// Whether `addr` is in the bit-banded region.
+    #[inline]
+    pub fn contains(&self, addr: usize) -> bool {
+        addr >= self.base && addr - self.base < self.size
+    }
+
+    /// The alias address of bit `bit` of the byte at `addr`.
+    ///
+    /// Panics if `addr` isn't in the region or `bit` isn't in `0..8`.
+    #[inline]
+    pub fn alias_addr(&self, addr: usize, bit: usize) -> usize {
+        assert!(self.contains(addr), "Address out of the bit-band region");
+        assert!(bit < 8, "Bit out of the byte");
+        self.alias + (addr - self.base) * 32 + bit * 4
+    }
+
+    /// The alias address of the 1-bit field `F` of register `reg`, which must be in
+    /// a layout overlaid on the region.
+    ///
+    /// Panics if `reg` isn't in the region.
+    #[inline]
+    pub fn alias_of<I, F>(&self, reg: &I) -> usize
+    where
+        I: InnerRegister,
+        F: BitField<Reg = I::Name>
+    {
+        let bit = const {
+            assert!(F::WIDTH == 1, "Bit-banding needs a single bit");
+            F::OFFSET
+        };
+        let byte = if I::Endian::BIG_ENDIAN { size_of::<I::Int>() - 1 - bit / 8 } else { bit / 8 };
+        self.alias_addr(reg as *const I as usize + byte, bit % 8)
+    }
+
+    /// Set the 1-bit field `F` of `reg` with a single write to its alias.
+    #[inline]
+    pub fn set_bit_atomic<I, F>(&self, reg: &I)
+    where
+        I: InnerRegister,
+        F: BitField<Reg = I::Name>
+    {
+        self.write_alias(self.alias_of::<I, F>(reg), 1);
+    }
+
+    /// Clear the 1-bit field `F` of `reg` with a single write to its alias.
+    #[inline]
+    pub fn clear_bit_atomic<I, F>(&self, reg: &I)
+    where
+        I: InnerRegister,
+        F: BitField<Reg = I::Name>
+    {
+        self.write_alias(self.alias_of::<I, F>(reg), 0);
+    }
+
+    /// Read the 1-bit field `F` of `reg` through its alias.
+    #[inline]
+    pub fn read_bit<I, F>(&self, reg: &I) -> bool
+    where
+        I: InnerRegister,
+        F: BitField<Reg = I::Name>
+    {
+        // SAFETY: the alias address is in the alias region, see `new()`.
+        unsafe {
+            (self.alias_of::<I, F>(reg) as *const u32).read_volatile() & 1 != 0
+        }
+    }
+
+    #[inline]
+    fn write_alias(&self, addr: usize, val: u32) {
+        // SAFETY: the alias address is in the alias region, see `new()`.
+        unsafe {
+            (addr as *mut u32).write_volatile(val);
+        }
+    }
+}
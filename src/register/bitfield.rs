@@ -5,9 +5,14 @@
 use core::marker::PhantomData;
 use core::ops::Add;
 
+use crate::error::Error;
 use crate::int::UIntLike;
 
 use super::RegName;
+use super::barrier::Barrier;
+use super::fallible::InfallibleIO;
+use super::transaction::Transaction;
+use super::lock::{rmw, CriticalSection, Locked};
 
 
 /// Together with trait `RegName`, this trait offers compile time checks
@@ -19,6 +24,16 @@ impl BitsLike<u16> for () {}
 impl BitsLike<u32> for () {}
 impl BitsLike<u64> for () {}
 
+/// A bit field known at the type level, for accesses checked at compile time
+/// against the field's place. `reg_bitfields!{}` implements it for a marker type
+/// named after each field, e.g. `CR::EN` is both a `Bits` constant and a
+/// `BitField` type.
+pub trait BitField {
+    type Reg: RegName;
+    const OFFSET: usize;
+    const WIDTH: usize;
+}
+
 /// ## Masked Value
 ///
 /// This struct indicates the result of putting values into target bits.
@@ -26,8 +41,8 @@ impl BitsLike<u64> for () {}
 /// You can combine different `MaskedValue` and `Bits` to represent the final state
 /// in the target bits.
 pub struct MaskedVal<T: UIntLike, R: RegName> {
-    val: T,
-    mask: T,
+    pub(crate) val: T,
+    pub(crate) mask: T,
     _reg: PhantomData<R>
 }
 
@@ -64,6 +79,18 @@ impl<T: UIntLike, R: RegName> Add<MaskedVal<T, R>> for Bits<T, R> {
     }
 }
 
+impl<T: UIntLike, R: RegName> From<Bits<T, R>> for MaskedVal<T, R> {
+    /// All target bits set.
+    #[inline]
+    fn from(bits: Bits<T, R>) -> Self {
+        MaskedVal {
+            val: bits.mask,
+            mask: bits.mask,
+            _reg: PhantomData
+        }
+    }
+}
+
 /// ## Bits
 ///
 /// The behind-the-scene struct used to perform value calculations for bit-field
@@ -75,17 +102,25 @@ impl<T: UIntLike, R: RegName> Add<MaskedVal<T, R>> for Bits<T, R> {
 /// Bit field operations are implemented inside corresponding `IO` traits. `Bits` itself
 /// only provides a combination method (by implementing `Add`) and a `val()` method to
 /// represent a value in the target field.
-#[derive(Clone, Copy)]
 pub struct Bits<T: UIntLike, R: RegName> {
-    offset: u8,
-    mask: T,
+    pub(crate) offset: u8,
+    pub(crate) mask: T,
     _reg: PhantomData<R>
 }
 
+impl<T: UIntLike, R: RegName> Clone for Bits<T, R> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: UIntLike, R: RegName> Copy for Bits<T, R> {}
+
 impl<T: UIntLike, R: RegName> Add<Bits<T, R>> for Bits<T, R> {
     type Output = Bits<T, R>;
 
     #[inline]
+    #[allow(clippy::suspicious_arithmetic_impl)]
     fn add(self, rhs: Bits<T, R>) -> Self::Output {
         Bits {
             offset: core::cmp::min(self.offset, rhs.offset),
@@ -115,15 +150,76 @@ impl<T: UIntLike, R: RegName> Bits<T, R> {
             _reg: PhantomData
         }
     }
+
+    /// Like `val()`, but fails with `Error::Overflow` if `val` doesn't fit in the
+    /// target bits instead of silently truncating it.
+    #[inline]
+    pub fn try_val<E>(&self, val: T) -> Result<MaskedVal<T, R>, Error<E>> {
+        let masked = self.val(val);
+        if (masked.val >> self.offset as usize) == val {
+            Ok(masked)
+        } else {
+            Err(Error::Overflow)
+        }
+    }
+}
+
+
+/// ## Local Copy
+///
+/// A value read from a register once and kept locally. It implements `ReadableIO`,
+/// so several fields can be decoded from the same hardware read.
+pub struct LocalCopy<T: UIntLike, R: RegName = ()> {
+    val: T,
+    _reg: PhantomData<R>
 }
 
+impl<T: UIntLike, R: RegName> Clone for LocalCopy<T, R> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: UIntLike, R: RegName> Copy for LocalCopy<T, R> {}
+
+impl<T: UIntLike, R: RegName> LocalCopy<T, R> {
+    pub const fn new(val: T) -> Self {
+        Self {
+            val,
+            _reg: PhantomData
+        }
+    }
+}
+
+impl<T: UIntLike, R: RegName> InfallibleIO for LocalCopy<T, R> {}
+
+impl<T: UIntLike, R: RegName> ReadableIO<T, R> for LocalCopy<T, R> {
+    #[inline]
+    fn read(&self) -> T {
+        self.val
+    }
+}
 
 /// ## Readable IO trait
 ///
 /// This trait contains reading-related operations.
+///
+/// Every method reads the IO once. For memory-mapped registers, that is a volatile
+/// read, which is kept in order with other register accesses but not with plain
+/// memory accesses. Use `read_acquire()` to order it, see `register::barrier` mod.
 pub trait ReadableIO<T: UIntLike, R: RegName = ()> {
     fn read(&self) -> T;
 
+    /// Read the IO, then issue an acquire barrier `B`, so later memory accesses
+    /// (e.g. of a DMA buffer the register reports complete) can't pass the read.
+    /// Use `AtomicBarrier` unless the target needs its own.
+    #[inline]
+    fn read_acquire<B: Barrier>(&self) -> T {
+        let val = self.read();
+        B::acquire();
+        val
+    }
+
     /// Get the value in the target field. Don't use combined `Bits` here.
     #[inline]
     fn get(&self, bits: Bits<T, R>) -> T {
@@ -141,11 +237,24 @@ pub trait ReadableIO<T: UIntLike, R: RegName = ()> {
 ///
 /// This trait contains writing related operations. Methods here will overwrite
 /// non-target bits' values to 0.
+///
+/// Every method writes the IO once. For memory-mapped registers, that is a volatile
+/// write, which is kept in order with other register accesses but not with plain
+/// memory accesses. Use `write_release()` to order it, see `register::barrier` mod.
 pub trait WritableIO<T: UIntLike, R: RegName = ()>
 {
     /// Write a value to the IO.
     fn write(&self, val: T);
 
+    /// Issue a release barrier `B`, then write the value, so earlier memory accesses
+    /// (e.g. filling a DMA buffer) complete before the device sees the write. Use
+    /// `AtomicBarrier` unless the target needs its own.
+    #[inline]
+    fn write_release<B: Barrier>(&self, val: T) {
+        B::release();
+        self.write(val);
+    }
+
     /// Put a value into target bits, other bits become 0.
     #[inline]
     fn put(&self, val: MaskedVal<T, R>) {
@@ -176,10 +285,24 @@ pub trait WritableIO<T: UIntLike, R: RegName = ()>
 /// This trait contains methods that only exist if an IO is both readable and writable.
 /// The addition `put_back()`, `set_back()` and `clear()` methods are useful if you want
 /// to keep other bits' value while putting new values into target bits.
+///
+/// The read-modify-write isn't atomic, see `register::lock` mod to protect it from
+/// other contexts.
 pub trait ReadWritableIO<T: UIntLike, R: RegName = ()> {
     fn put_back(&self, val: MaskedVal<T, R>);
     fn set_back(&self, bits: Bits<T, R>);
     fn clear(&self, bits: Bits<T, R>);
+    fn toggle(&self, bits: Bits<T, R>);
+    fn transaction<F: FnOnce(&mut Transaction<T, R>)>(&self, f: F);
+
+    /// Get a handle whose read-modify-write methods run inside critical section `cs`.
+    #[inline]
+    fn locked<'a, C: CriticalSection + ?Sized>(&'a self, cs: &'a C) -> Locked<'a, Self, C>
+    where
+        Self: Sized
+    {
+        Locked::new(self, cs)
+    }
 }
 
 impl<U, T: UIntLike, R: RegName> ReadWritableIO<T, R> for U
@@ -189,18 +312,35 @@ where
     /// Put the value into target bits, while keeping others untouched.
     #[inline]
     fn put_back(&self, val: MaskedVal<T, R>) {
-        self.write(self.read() & (!val.mask) | val.val );
+        rmw(|| self.write(self.read() & (!val.mask) | val.val));
     }
 
     /// Set target bits, while keeping others untouched.
     #[inline]
     fn set_back(&self, bits: Bits<T, R>) {
-        self.write(self.read() | bits.mask);
+        rmw(|| self.write(self.read() | bits.mask));
     }
 
     /// Set target bits to 0, while keeping others untouched.
     #[inline]
     fn clear(&self, bits: Bits<T, R>) {
-        self.write(self.read() & (!bits.mask));
+        rmw(|| self.write(self.read() & (!bits.mask)));
+    }
+
+    /// Flip target bits, while keeping others untouched.
+    #[inline]
+    fn toggle(&self, bits: Bits<T, R>) {
+        rmw(|| self.write(self.read() ^ bits.mask));
+    }
+
+    /// Accumulate the operations done in `f`, then apply them all with a single
+    /// read and a single write. Nothing is accessed if no bit is touched.
+    #[inline]
+    fn transaction<F: FnOnce(&mut Transaction<T, R>)>(&self, f: F) {
+        let mut tx = Transaction::new();
+        f(&mut tx);
+        if !tx.is_empty() {
+            rmw(|| self.write(tx.apply(self.read())));
+        }
     }
 }
@@ -0,0 +1,243 @@
+//! ## crate::register::bus
+//!
+//! This mod contains utilities for registers behind a bus like I2C or SPI. Users
+//! implement `RegisterBus` for their bus driver, declare the register map with
+//! `bus_registers!{}` and access registers through `BusDevice`.
+//!
+//! Bus transfers can fail, so bus registers implement the fallible IO traits
+//! (`TryReadableIO`, `TryWritableIO` and `TryReadWritableIO`), whose errors carry
+//! the bus error in `Error::Bus`.
+//!
+//! Every address of the register map must fit `RegisterBus::Addr`, which is checked
+//! at compile time:
+//!
+//! ```compile_fail
+//! use re_reg::prelude::*;
+//! bus_registers! {
+//!     BigRegs {
+//!         ( 0xff => CTRL: RW<u16> ),
+//!     }
+//! }
+//! struct Bus;
+//! impl RegisterBus for Bus {
+//!     type Error = ();
+//!     type Addr = u8;
+//!     fn read_regs(&mut self, _: u8, _: &mut [u8]) -> Result<(), ()> { Ok(()) }
+//!     fn write_regs(&mut self, _: u8, _: &[u8]) -> Result<(), ()> { Ok(()) }
+//! }
+//! // CTRL spans 0xff and 0x100.
+//! let _ = BusDevice::<_, BigRegs>::new(Bus).reg(BigRegs::CTRL).try_read();
+//! ```
+
+use core::cell::RefCell;
+use core::marker::PhantomData;
+use core::mem::size_of;
+
+use crate::error::Error;
+use crate::int::UIntLike;
+
+use super::backend::RegDesc;
+use super::bitfield::{BitsLike, LocalCopy};
+use super::fallible::{TryIO, TryReadableIO, TryWritableIO};
+use super::endian::Endianness;
+use super::{InnerRegister, RCInnerRegister, Readable, RegName, Writable};
+
+/// ## Register bus
+///
+/// A bus transferring register contents as bytes.
+pub trait RegisterBus {
+    type Error;
+
+    /// The register address type, which also decides the address width (e.g. `u8`
+    /// for most I2C sensors, `u16` for bigger devices).
+    type Addr: UIntLike;
+
+    /// Whether the device increments the register address by itself during a
+    /// multi-byte transfer. If not, registers wider than a byte are transferred
+    /// one byte at a time with consecutive addresses.
+    const AUTO_INCREMENT: bool = true;
+
+    /// Whether registers wider than a byte are transferred most significant byte first.
+    const BIG_ENDIAN: bool = true;
+
+    /// Read `buf.len()` bytes starting from register `addr`.
+    fn read_regs(&mut self, addr: Self::Addr, buf: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Write `data` starting from register `addr`.
+    fn write_regs(&mut self, addr: Self::Addr, data: &[u8]) -> Result<(), Self::Error>;
+}
+
+/// ## Register map
+///
+/// Implemented by register maps generated by `bus_registers!{}`.
+pub trait RegisterMap {
+    /// One past the last register address.
+    const END: usize;
+
+    /// Whether the register at `addr` may change by itself, so its value can't be
+    /// cached. Unknown addresses are volatile.
+    fn is_volatile(addr: usize) -> bool;
+
+    /// The address and width of the register holding the byte at `addr`.
+    fn reg_at(addr: usize) -> Option<(usize, usize)>;
+}
+
+/// ## Bus device
+///
+/// A register map `L` generated by `bus_registers!{}`, instantiated over bus `B`.
+pub struct BusDevice<B: RegisterBus, L> {
+    bus: RefCell<B>,
+    _layout: PhantomData<fn() -> L>
+}
+
+impl<B: RegisterBus, L> BusDevice<B, L> {
+    pub const fn new(bus: B) -> Self {
+        Self {
+            bus: RefCell::new(bus),
+            _layout: PhantomData
+        }
+    }
+
+    /// Get the bus back.
+    pub fn release(self) -> B {
+        self.bus.into_inner()
+    }
+
+    #[inline]
+    pub fn bus_mut(&mut self) -> &mut B {
+        self.bus.get_mut()
+    }
+
+    /// Get the register described by `desc`.
+    #[inline]
+    pub fn reg<I: InnerRegister>(&self, desc: RegDesc<L, I>) -> BusRegister<'_, B, I>
+    where
+        L: RegisterMap
+    {
+        const { assert!(addr_fits::<B::Addr>(L::END), "Register map addresses don't fit RegisterBus::Addr") };
+        BusRegister {
+            bus: &self.bus,
+            addr: desc.offset(),
+            _reg: PhantomData
+        }
+    }
+}
+
+/// ## Bus register
+///
+/// A register behind a bus. The available operations depend on the register kind `I`.
+pub struct BusRegister<'a, B: RegisterBus, I: InnerRegister> {
+    bus: &'a RefCell<B>,
+    addr: usize,
+    _reg: PhantomData<fn() -> I>
+}
+
+impl<B: RegisterBus, I: InnerRegister> BusRegister<'_, B, I> {
+    #[inline]
+    pub fn addr(&self) -> B::Addr {
+        B::Addr::from_u64(self.addr as u64)
+    }
+
+    #[inline]
+    fn read_raw(&self) -> Result<I::Int, B::Error> {
+        read_reg(&mut *self.bus.borrow_mut(), self.addr)
+    }
+
+    #[inline]
+    fn write_raw(&self, val: I::Int) -> Result<(), B::Error> {
+        write_reg(&mut *self.bus.borrow_mut(), self.addr, val)
+    }
+}
+
+impl<B: RegisterBus, I: InnerRegister> TryIO for BusRegister<'_, B, I> {
+    type BusError = B::Error;
+}
+
+impl<B, I> TryReadableIO<I::Int, I::Name> for BusRegister<'_, B, I>
+where
+    B: RegisterBus,
+    I: InnerRegister + Readable
+{
+    #[inline]
+    fn try_read(&self) -> Result<I::Int, Error<B::Error>> {
+        self.read_raw().map_err(Error::Bus)
+    }
+}
+
+impl<B, I> TryWritableIO<I::Int, I::Name> for BusRegister<'_, B, I>
+where
+    B: RegisterBus,
+    I: InnerRegister + Writable
+{
+    #[inline]
+    fn try_write(&self, val: I::Int) -> Result<(), Error<B::Error>> {
+        self.write_raw(val).map_err(Error::Bus)
+    }
+}
+
+impl<B, T, R, E> BusRegister<'_, B, RCInnerRegister<T, R, E>>
+where
+    B: RegisterBus,
+    T: UIntLike,
+    R: RegName + BitsLike<T>,
+    E: Endianness
+{
+    /// Read the register once, see `RCInnerRegister::take()`. The bus decides the
+    /// byte order, `E` is ignored.
+    #[inline]
+    #[must_use = "the read has side effects, the value can't be read again"]
+    pub fn take(&self) -> Result<LocalCopy<T, R>, Error<B::Error>> {
+        self.read_raw().map(LocalCopy::new).map_err(Error::Bus)
+    }
+}
+
+/// Read the `T` register at `addr` of `bus`.
+pub(crate) fn read_reg<B: RegisterBus, T: UIntLike>(bus: &mut B, addr: usize) -> Result<T, B::Error> {
+    let mut buf = [0u8; 8];
+    let buf = &mut buf[..size_of::<T>()];
+    if B::AUTO_INCREMENT {
+        bus.read_regs(B::Addr::from_u64(addr as u64), buf)?;
+    } else {
+        for (i, byte) in buf.iter_mut().enumerate() {
+            bus.read_regs(B::Addr::from_u64((addr + i) as u64), core::slice::from_mut(byte))?;
+        }
+    }
+    Ok(from_bytes::<T>(buf, B::BIG_ENDIAN))
+}
+
+/// Write the `T` register at `addr` of `bus`.
+pub(crate) fn write_reg<B: RegisterBus, T: UIntLike>(bus: &mut B, addr: usize, val: T) -> Result<(), B::Error> {
+    let mut buf = [0u8; 8];
+    let buf = &mut buf[..size_of::<T>()];
+    to_bytes(val, buf, B::BIG_ENDIAN);
+    if B::AUTO_INCREMENT {
+        bus.write_regs(B::Addr::from_u64(addr as u64), buf)
+    } else {
+        for (i, byte) in buf.iter().enumerate() {
+            bus.write_regs(B::Addr::from_u64((addr + i) as u64), core::slice::from_ref(byte))?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether all addresses below `end` fit address type `A`.
+pub(crate) const fn addr_fits<A>(end: usize) -> bool {
+    size_of::<A>() >= size_of::<usize>() || end <= 1 << (8 * size_of::<A>())
+}
+
+pub(crate) fn from_bytes<T: UIntLike>(buf: &[u8], big_endian: bool) -> T {
+    let val = buf.iter().enumerate().fold(0u64, |val, (i, &byte)| {
+        let shift = if big_endian { buf.len() - 1 - i } else { i } * 8;
+        val | (byte as u64) << shift
+    });
+    T::from_u64(val)
+}
+
+pub(crate) fn to_bytes<T: UIntLike>(val: T, buf: &mut [u8], big_endian: bool) {
+    let val = val.to_u64();
+    let len = buf.len();
+    for (i, byte) in buf.iter_mut().enumerate() {
+        let shift = if big_endian { len - 1 - i } else { i } * 8;
+        *byte = (val >> shift) as u8;
+    }
+}
@@ -0,0 +1,225 @@
+//! ## crate::register::cache
+//!
+//! This mod contains a register cache for slow buses, similar to Linux's regmap
+//! cache. `RegCache` wraps a `RegisterBus` and is a `RegisterBus` itself, so it sits
+//! under `BusDevice` and the usual IO traits without changing any driver code.
+//!
+//! Registers marked `volatile` in `bus_registers!{}` and `RC` registers always go to
+//! the bus. Other registers are cached:
+//! - Reads are served from the cache, and only the first one reaches the bus.
+//! - Writes only update the cache and mark the register dirty, `sync()` flushes
+//!   dirty registers to the bus.
+//!
+//! Therefore `try_put_back()` on a cached register costs no bus read, and several
+//! of them cost a single bus write on `sync()`.
+//!
+//! A register is cached as one unit, also when a bus without `AUTO_INCREMENT`
+//! transfers it byte by byte, and `sync()` writes it the way `BusDevice` would.
+//!
+//! Writes going to the bus while the cache is bypassed update the cached registers
+//! they cover, so the cache never holds an older value than the hardware. Use
+//! `read_uncached()` to read a single register from the bus.
+
+use core::marker::PhantomData;
+
+use crate::error::Error;
+use crate::int::UIntLike;
+
+use super::backend::RegDesc;
+use super::bus::{read_reg, RegisterBus, RegisterMap};
+use super::{InnerRegister, Readable};
+
+#[derive(Clone, Copy)]
+struct Entry {
+    /// The register's address.
+    base: usize,
+    width: usize,
+    data: [u8; 8],
+    /// Bit `i` is set when byte `i` of `data` holds the register's value.
+    valid: u8,
+    /// Bit `i` is set when byte `i` of `data` is waiting for `sync()`.
+    dirty: u8
+}
+
+/// The bits of bytes `start..start + len`.
+#[inline]
+fn byte_mask(start: usize, len: usize) -> u8 {
+    (((1u16 << len) - 1) << start) as u8
+}
+
+/// ## Register cache
+///
+/// Caches up to `N` registers of register map `L` on bus `B`. Once the cache is
+/// full, registers not cached yet go straight to the bus.
+pub struct RegCache<B: RegisterBus, L: RegisterMap, const N: usize> {
+    bus: B,
+    entries: [Option<Entry>; N],
+    bypass: bool,
+    _layout: PhantomData<fn() -> L>
+}
+
+impl<B: RegisterBus, L: RegisterMap, const N: usize> RegCache<B, L, N> {
+    pub const fn new(bus: B) -> Self {
+        Self {
+            bus,
+            entries: [None; N],
+            bypass: false,
+            _layout: PhantomData
+        }
+    }
+
+    /// Get the bus back. Dirty registers are not flushed, call `sync()` first.
+    pub fn release(self) -> B {
+        self.bus
+    }
+
+    #[inline]
+    pub fn bus_mut(&mut self) -> &mut B {
+        &mut self.bus
+    }
+
+    /// When on, all accesses go straight to the bus. Reads leave the cache untouched,
+    /// writes update the cached registers they cover.
+    #[inline]
+    pub fn set_bypass(&mut self, bypass: bool) {
+        self.bypass = bypass;
+    }
+
+    /// Read the register described by `desc` from the bus, leaving the cache
+    /// untouched.
+    pub fn read_uncached<I>(&mut self, desc: RegDesc<L, I>) -> Result<I::Int, Error<B::Error>>
+    where
+        I: InnerRegister + Readable
+    {
+        read_reg(&mut self.bus, desc.offset()).map_err(Error::Bus)
+    }
+
+    /// Write all dirty registers to the bus.
+    pub fn sync(&mut self) -> Result<(), B::Error> {
+        for entry in self.entries.iter_mut().flatten() {
+            if entry.dirty == 0 {
+                continue;
+            }
+            if entry.valid == byte_mask(0, entry.width) && B::AUTO_INCREMENT {
+                self.bus.write_regs(B::Addr::from_u64(entry.base as u64), &entry.data[..entry.width])?;
+            } else {
+                // Without auto increment, or with only some bytes known, write byte
+                // by byte with increasing addresses.
+                for i in (0..entry.width).filter(|i| entry.valid & (1 << i) != 0) {
+                    let addr = B::Addr::from_u64((entry.base + i) as u64);
+                    self.bus.write_regs(addr, core::slice::from_ref(&entry.data[i]))?;
+                }
+            }
+            entry.dirty = 0;
+        }
+        Ok(())
+    }
+
+    /// Mark all cached registers dirty, e.g. after the device was reset, so the next
+    /// `sync()` restores them.
+    pub fn mark_dirty_all(&mut self) {
+        for entry in self.entries.iter_mut().flatten() {
+            entry.dirty = entry.valid;
+        }
+    }
+
+    /// Drop all cached values, including dirty ones.
+    pub fn invalidate(&mut self) {
+        self.entries = [None; N];
+    }
+
+    /// Whether any cached register is waiting for `sync()`.
+    pub fn is_dirty(&self) -> bool {
+        self.entries.iter().flatten().any(|entry| entry.dirty != 0)
+    }
+
+    /// The address and width of the cacheable register holding all bytes of an
+    /// access.
+    fn cacheable(&self, addr: usize, len: usize) -> Option<(usize, usize)> {
+        let (base, width) = L::reg_at(addr)?;
+        (!self.bypass && addr + len <= base + width && !L::is_volatile(base)).then_some((base, width))
+    }
+
+    fn find(&mut self, base: usize) -> Option<&mut Entry> {
+        self.entries.iter_mut().flatten().find(|entry| entry.base == base)
+    }
+
+    /// The entry of a register, a new one if it isn't cached yet. `None` if the
+    /// cache is full.
+    fn entry(&mut self, base: usize, width: usize) -> Option<&mut Entry> {
+        let slot = match self.entries.iter().position(|entry| matches!(entry, Some(entry) if entry.base == base)) {
+            Some(i) => i,
+            None => {
+                let i = self.entries.iter().position(Option::is_none)?;
+                self.entries[i] = Some(Entry { base, width, data: [0; 8], valid: 0, dirty: 0 });
+                i
+            }
+        };
+        self.entries[slot].as_mut()
+    }
+
+    /// Update the cached bytes a write to the bus covers.
+    fn write_through(&mut self, addr: usize, data: &[u8]) {
+        for (i, &byte) in data.iter().enumerate() {
+            let Some((base, _)) = L::reg_at(addr + i) else {
+                continue;
+            };
+            if let Some(entry) = self.find(base) {
+                let at = addr + i - base;
+                entry.data[at] = byte;
+                entry.valid |= 1 << at;
+                entry.dirty &= !(1 << at);
+            }
+        }
+    }
+}
+
+impl<B: RegisterBus, L: RegisterMap, const N: usize> RegisterBus for RegCache<B, L, N> {
+    type Error = B::Error;
+    type Addr = B::Addr;
+
+    const AUTO_INCREMENT: bool = B::AUTO_INCREMENT;
+    const BIG_ENDIAN: bool = B::BIG_ENDIAN;
+
+    fn read_regs(&mut self, addr: B::Addr, buf: &mut [u8]) -> Result<(), B::Error> {
+        let at = addr.to_u64() as usize;
+        let Some((base, width)) = self.cacheable(at, buf.len()) else {
+            return self.bus.read_regs(addr, buf);
+        };
+        let (start, mask) = (at - base, byte_mask(at - base, buf.len()));
+        let known = match self.entry(base, width) {
+            Some(entry) => entry.valid & mask == mask,
+            None => return self.bus.read_regs(addr, buf)
+        };
+        if !known {
+            self.bus.read_regs(addr, buf)?;
+        }
+        let entry = self.find(base).expect("the entry was just found");
+        for (i, byte) in buf.iter_mut().enumerate() {
+            // Bytes waiting for `sync()` are newer than the bus.
+            if known || entry.dirty & (1 << (start + i)) != 0 {
+                *byte = entry.data[start + i];
+            } else {
+                entry.data[start + i] = *byte;
+            }
+        }
+        entry.valid |= mask;
+        Ok(())
+    }
+
+    fn write_regs(&mut self, addr: B::Addr, data: &[u8]) -> Result<(), B::Error> {
+        let at = addr.to_u64() as usize;
+        if let Some((base, width)) = self.cacheable(at, data.len()) {
+            if let Some(entry) = self.entry(base, width) {
+                let (start, mask) = (at - base, byte_mask(at - base, data.len()));
+                entry.data[start..start + data.len()].copy_from_slice(data);
+                entry.valid |= mask;
+                entry.dirty |= mask;
+                return Ok(());
+            }
+        }
+        self.bus.write_regs(addr, data)?;
+        self.write_through(at, data);
+        Ok(())
+    }
+}
@@ -0,0 +1,226 @@
+//! ## crate::register::coverage
+//!
+//! This mod contains field-value coverage of driver tests. Wrap the backend of a
+//! `Device` (e.g. a `FakeDevice` or a `SimDevice`) with `Coverage`, run the driver,
+//! then print `coverage.report()`. It lists every register of the layout with the
+//! values each field was read (`R`) or written (`W`) with, named values from
+//! `reg_bitfields!{}` first, then the other values seen. Named values never
+//! accessed are marked `-`, they are the modes the tests never exercise:
+//!
+//! ```text
+//! CR @0x0: RW
+//!   EN: 0x0=R 0x1=W
+//!   MODE: Slow=- Fast=W 0x0=R
+//! SR @0x4: -
+//! named values covered: 1/2
+//! ```
+//!
+//! Values are decoded in the register's logical bit order. Only accesses of a whole
+//! register count, lane and split-half accesses are not decoded.
+//!
+//! A write counts for the fields whose bits it changes from the last value read or
+//! written, so a read-modify-write of one field doesn't mark the other fields'
+//! current values as written. The first access of a register being a write, all its
+//! fields count.
+//!
+//! Reports of several tests can be combined with `CoverageReport::merge()`.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::marker::PhantomData;
+use std::vec::Vec;
+
+use crate::int::UIntLike;
+
+use super::{FieldDesc, RegisterLayout};
+use super::backend::RegisterBackend;
+
+/// ## Coverage backend
+///
+/// Collects the distinct values read and written through backend `B`, for a driver
+/// using layout `L`.
+pub struct Coverage<B: RegisterBackend, L: RegisterLayout> {
+    backend: B,
+    read: RefCell<BTreeMap<usize, BTreeSet<u64>>>,
+    written: RefCell<BTreeMap<usize, BTreeSet<(u64, u64)>>>,
+    /// The last value read or written of each register.
+    last: RefCell<BTreeMap<usize, u64>>,
+    _layout: PhantomData<fn() -> L>
+}
+
+impl<B: RegisterBackend, L: RegisterLayout> Coverage<B, L> {
+    pub fn new(backend: B) -> Self {
+        Self {
+            backend,
+            read: RefCell::new(BTreeMap::new()),
+            written: RefCell::new(BTreeMap::new()),
+            last: RefCell::new(BTreeMap::new()),
+            _layout: PhantomData
+        }
+    }
+
+    #[inline]
+    pub fn inner(&self) -> &B {
+        &self.backend
+    }
+
+    /// The coverage of every register of the layout so far.
+    pub fn report(&self) -> CoverageReport {
+        let (read, written) = (self.read.borrow(), self.written.borrow());
+        let registers = (0..size_of::<L>())
+            .filter_map(|offset| {
+                let info = L::reg_info(offset)?;
+                Some(RegCoverage {
+                    name: info.name,
+                    offset,
+                    fields: info.fields,
+                    read: read.get(&offset).cloned().unwrap_or_default(),
+                    written: written.get(&offset).cloned().unwrap_or_default()
+                })
+            })
+            .collect();
+        CoverageReport { registers }
+    }
+}
+
+impl<B: RegisterBackend, L: RegisterLayout> Coverage<B, L> {
+    /// The logical value of an access of a whole register.
+    fn decode<T: UIntLike>(offset: usize, raw: T) -> Option<u64> {
+        L::reg_info(offset)?.decode(size_of::<T>(), raw.to_u64())
+    }
+}
+
+impl<B: RegisterBackend, L: RegisterLayout> RegisterBackend for Coverage<B, L> {
+    fn read<T: UIntLike>(&self, offset: usize) -> T {
+        let raw = self.backend.read::<T>(offset);
+        if let Some(val) = Self::decode(offset, raw) {
+            self.read.borrow_mut().entry(offset).or_default().insert(val);
+            self.last.borrow_mut().insert(offset, val);
+        }
+        raw
+    }
+
+    fn write<T: UIntLike>(&self, offset: usize, raw: T) {
+        if let Some(val) = Self::decode(offset, raw) {
+            let changed = self.last.borrow_mut().insert(offset, val).map_or(u64::MAX, |last| last ^ val);
+            self.written.borrow_mut().entry(offset).or_default().insert((val, changed));
+        }
+        self.backend.write(offset, raw);
+    }
+}
+
+/// ## Coverage of a register
+#[derive(Clone, Debug)]
+pub struct RegCoverage {
+    /// The register's name in the layout.
+    pub name: &'static str,
+    pub offset: usize,
+    pub fields: &'static [FieldDesc],
+    /// The distinct values read.
+    pub read: BTreeSet<u64>,
+    /// The distinct values written, with the bits each write changed.
+    pub written: BTreeSet<(u64, u64)>
+}
+
+impl RegCoverage {
+    /// Whether `field` was read with value `val`.
+    pub fn field_read(&self, field: &FieldDesc, val: u64) -> bool {
+        self.read.iter().any(|reg| field.decode(*reg) == val)
+    }
+
+    /// Whether `field` was written with value `val`, by a write changing it.
+    pub fn field_written(&self, field: &FieldDesc, val: u64) -> bool {
+        self.written_values(field).any(|v| v == val)
+    }
+
+    /// The values of `field` set by writes changing it.
+    fn written_values<'a>(&'a self, field: &'a FieldDesc) -> impl Iterator<Item = u64> + 'a {
+        self.written.iter()
+            .filter(|(_, changed)| changed & field.mask() != 0)
+            .map(|(reg, _)| field.decode(*reg))
+    }
+
+    /// The named values of each field never read nor written, as `(field, value)`.
+    pub fn uncovered(&self) -> impl Iterator<Item = (&'static str, &'static str)> + '_ {
+        self.fields.iter().flat_map(move |field| {
+            field.values.iter()
+                .filter(move |(_, val)| !self.field_read(field, *val) && !self.field_written(field, *val))
+                .map(move |(name, _)| (field.name, *name))
+        })
+    }
+
+    fn marks(read: bool, written: bool) -> &'static str {
+        match (read, written) {
+            (true, true) => "RW",
+            (true, false) => "R",
+            (false, true) => "W",
+            (false, false) => "-"
+        }
+    }
+}
+
+impl fmt::Display for RegCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let accessed = Self::marks(!self.read.is_empty(), !self.written.is_empty());
+        write!(f, "{} @{:#x}: {}", self.name, self.offset, accessed)?;
+        for field in self.fields {
+            write!(f, "\n  {}:", field.name)?;
+            for (name, val) in field.values {
+                write!(f, " {}={}", name, Self::marks(self.field_read(field, *val), self.field_written(field, *val)))?;
+            }
+            let seen: BTreeSet<u64> = self.read.iter()
+                .map(|reg| field.decode(*reg))
+                .chain(self.written_values(field))
+                .filter(|val| field.value_name(*val).is_none())
+                .collect();
+            for val in seen {
+                write!(f, " {:#x}={}", val, Self::marks(self.field_read(field, val), self.field_written(field, val)))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// ## Coverage report
+#[derive(Clone, Debug, Default)]
+pub struct CoverageReport {
+    /// The registers of the layout, by offset.
+    pub registers: Vec<RegCoverage>
+}
+
+impl CoverageReport {
+    /// Add the values seen in `other`, a report of the same layout.
+    pub fn merge(&mut self, other: &CoverageReport) {
+        for reg in &other.registers {
+            match self.registers.iter_mut().find(|r| r.offset == reg.offset) {
+                Some(r) => {
+                    r.read.extend(&reg.read);
+                    r.written.extend(&reg.written);
+                }
+                None => self.registers.push(reg.clone())
+            }
+        }
+        self.registers.sort_by_key(|reg| reg.offset);
+    }
+
+    /// The named values never read nor written, as `(register, field, value)`.
+    pub fn uncovered(&self) -> Vec<(&'static str, &'static str, &'static str)> {
+        self.registers.iter()
+            .flat_map(|reg| reg.uncovered().map(move |(field, val)| (reg.name, field, val)))
+            .collect()
+    }
+}
+
+impl fmt::Display for CoverageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for reg in &self.registers {
+            writeln!(f, "{}", reg)?;
+        }
+        let total: usize = self.registers.iter()
+            .flat_map(|reg| reg.fields)
+            .map(|field| field.values.len())
+            .sum();
+        write!(f, "named values covered: {}/{}", total - self.uncovered().len(), total)
+    }
+}
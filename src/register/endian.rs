@@ -0,0 +1,70 @@
+//! ## crate::register::endian
+//!
+//! This mod contains the byte orders of memory-mapped registers. Every `InnerRegister`
+//! kind takes a byte order parameter, which defaults to `NativeEndian`. Registers of
+//! another byte order (e.g. big-endian IP blocks driven from a little-endian core)
+//! are byte-swapped on every access, so values and `Bits` offsets stay in logical
+//! bit order.
+//!
+//! In `registers_layout!{}`, use the `_BE`/`_LE` kinds (e.g. `RW_BE<u32, CR::Reg>`)
+//! for single registers, or give the layout a default byte order with `Name(BE)`.
+//!
+//! Register maps behind a bus use `RegisterBus::BIG_ENDIAN` instead.
+
+use crate::int::UIntLike;
+
+/// ## Register byte order
+pub trait Endianness {
+    /// Whether the most significant byte is at the lowest address.
+    const BIG_ENDIAN: bool;
+
+    /// Convert a value read from the hardware to native byte order.
+    fn from_hw<T: UIntLike>(val: T) -> T;
+
+    /// Convert a native value to the hardware's byte order.
+    fn to_hw<T: UIntLike>(val: T) -> T;
+}
+
+/// The CPU's byte order, values are accessed as they are.
+pub struct NativeEndian;
+
+/// Big-endian registers.
+pub struct BigEndian;
+
+/// Little-endian registers.
+pub struct LittleEndian;
+
+macro_rules! impl_endianness {
+    ($endian:ident, $target:literal, $big:literal) => {
+        impl Endianness for $endian {
+            const BIG_ENDIAN: bool = $big;
+
+            #[inline]
+            fn from_hw<T: UIntLike>(val: T) -> T {
+                if cfg!(target_endian = $target) { val } else { val.swap_bytes() }
+            }
+
+            #[inline]
+            fn to_hw<T: UIntLike>(val: T) -> T {
+                Self::from_hw(val)
+            }
+        }
+    };
+}
+
+impl_endianness!(BigEndian, "big", true);
+impl_endianness!(LittleEndian, "little", false);
+
+impl Endianness for NativeEndian {
+    const BIG_ENDIAN: bool = cfg!(target_endian = "big");
+
+    #[inline]
+    fn from_hw<T: UIntLike>(val: T) -> T {
+        val
+    }
+
+    #[inline]
+    fn to_hw<T: UIntLike>(val: T) -> T {
+        val
+    }
+}
@@ -0,0 +1,119 @@
+//! ## crate::register::fake
+//!
+//! This mod contains `FakeDevice`, an in-memory device for driver unit tests. It
+//! owns zeroed, correctly aligned and interior-mutable memory for a layout, so the
+//! layout can be borrowed safely while tests peek and poke the memory behind it.
+//!
+//! `FakeDevice` is also a `RegisterBackend`, for drivers written against `Device`.
+
+use core::cell::UnsafeCell;
+use core::mem::{align_of, size_of, MaybeUninit};
+use core::ops::{Deref, DerefMut};
+
+use crate::int::UIntLike;
+
+use super::RegisterLayout;
+use super::backend::{RegDesc, RegisterBackend};
+use super::InnerRegister;
+
+/// ## Fake device
+///
+/// Dereferences to the layout `L`.
+pub struct FakeDevice<L: RegisterLayout> {
+    mem: UnsafeCell<L>
+}
+
+impl<L: RegisterLayout> Default for FakeDevice<L> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<L: RegisterLayout> FakeDevice<L> {
+    /// Create a device with all registers 0.
+    pub fn new() -> Self {
+        Self {
+            // SAFETY: all zero bytes are a valid layout, see `RegisterLayout`.
+            mem: UnsafeCell::new(unsafe { MaybeUninit::zeroed().assume_init() })
+        }
+    }
+
+    /// The layout.
+    #[inline]
+    pub fn regs(&self) -> &L {
+        // SAFETY: the memory is only changed through `UnsafeCell`s of the layout.
+        unsafe { &*self.mem.get() }
+    }
+
+    /// The layout, borrowed mutably, e.g. to select a register bank.
+    #[inline]
+    pub fn regs_mut(&mut self) -> &mut L {
+        self.mem.get_mut()
+    }
+
+    #[inline]
+    fn ptr<T: UIntLike>(&self, offset: usize) -> *mut T {
+        assert!(offset + size_of::<T>() <= size_of::<L>(), "Offset out of the layout");
+        assert!(offset.is_multiple_of(align_of::<T>()), "Unaligned offset");
+        // SAFETY: in bounds, checked above.
+        unsafe { (self.mem.get() as *mut u8).add(offset) as *mut T }
+    }
+
+    /// Read the `T` at `offset` of the memory, with no side effect.
+    ///
+    /// Panics if `offset` is out of the layout or not aligned for `T`.
+    #[inline]
+    pub fn peek<T: UIntLike>(&self, offset: usize) -> T {
+        // SAFETY: every byte is interior-mutable, see `RegisterLayout`.
+        unsafe { self.ptr::<T>(offset).read_volatile() }
+    }
+
+    /// Write the `T` at `offset` of the memory, with no side effect.
+    ///
+    /// Panics if `offset` is out of the layout or not aligned for `T`.
+    #[inline]
+    pub fn poke<T: UIntLike>(&self, offset: usize, val: T) {
+        // SAFETY: every byte is interior-mutable, see `RegisterLayout`.
+        unsafe { self.ptr::<T>(offset).write_volatile(val) }
+    }
+
+    /// Read the register described by `desc`, in native byte order.
+    #[inline]
+    pub fn peek_reg<I: InnerRegister>(&self, desc: RegDesc<L, I>) -> I::Int {
+        self.peek(desc.offset())
+    }
+
+    /// Write the register described by `desc`, in native byte order.
+    #[inline]
+    pub fn poke_reg<I: InnerRegister>(&self, desc: RegDesc<L, I>, val: I::Int) {
+        self.poke(desc.offset(), val);
+    }
+}
+
+impl<L: RegisterLayout> Deref for FakeDevice<L> {
+    type Target = L;
+
+    #[inline]
+    fn deref(&self) -> &L {
+        self.regs()
+    }
+}
+
+impl<L: RegisterLayout> DerefMut for FakeDevice<L> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut L {
+        self.regs_mut()
+    }
+}
+
+impl<L: RegisterLayout> RegisterBackend for FakeDevice<L> {
+    #[inline]
+    fn read<T: UIntLike>(&self, offset: usize) -> T {
+        self.peek(offset)
+    }
+
+    #[inline]
+    fn write<T: UIntLike>(&self, offset: usize, val: T) {
+        self.poke(offset, val);
+    }
+}
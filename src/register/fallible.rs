@@ -0,0 +1,169 @@
+//! ## crate::register::fallible
+//!
+//! This mod contains the fallible IO traits. They mirror `ReadableIO`, `WritableIO`
+//! and `ReadWritableIO` but return `Result<_, crate::Error>`, so the same driver code
+//! works on registers behind a bus and on memory-mapped ones.
+//!
+//! Registers implementing the infallible traits get these traits for free by
+//! implementing the `InfallibleIO` marker, with `Infallible` as the bus error.
+
+use core::convert::Infallible;
+
+use crate::error::Error;
+use crate::int::UIntLike;
+
+use super::RegName;
+use super::bitfield::{Bits, MaskedVal, ReadableIO, WritableIO};
+use super::transaction::Transaction;
+use super::lock::rmw;
+
+/// Marks registers whose accesses can't fail, to get the fallible IO traits
+/// implemented on top of `ReadableIO` and `WritableIO`.
+pub trait InfallibleIO {}
+
+/// Shared by the fallible IO traits, so they agree on the bus error type.
+pub trait TryIO {
+    type BusError;
+}
+
+/// ## Fallible Readable IO trait
+pub trait TryReadableIO<T: UIntLike, R: RegName = ()>: TryIO {
+    fn try_read(&self) -> Result<T, Error<Self::BusError>>;
+
+    /// Get the value in the target field. Don't use combined `Bits` here.
+    #[inline]
+    fn try_get(&self, bits: Bits<T, R>) -> Result<T, Error<Self::BusError>> {
+        Ok((self.try_read()? & bits.mask) >> (bits.offset as usize))
+    }
+
+    /// Check if target bits are set.
+    #[inline]
+    fn try_is_set(&self, bits: Bits<T, R>) -> Result<bool, Error<Self::BusError>> {
+        Ok((self.try_read()? & bits.mask) == bits.mask)
+    }
+
+    /// Read the register until target bits are set, at most `attempts` times.
+    fn try_wait_set(&self, bits: Bits<T, R>, attempts: usize) -> Result<(), Error<Self::BusError>> {
+        for _ in 0..attempts {
+            if self.try_is_set(bits)? {
+                return Ok(());
+            }
+        }
+        Err(Error::Timeout)
+    }
+}
+
+/// ## Fallible Writable IO trait
+///
+/// Methods here will overwrite non-target bits' values to 0.
+pub trait TryWritableIO<T: UIntLike, R: RegName = ()>: TryIO {
+    fn try_write(&self, val: T) -> Result<(), Error<Self::BusError>>;
+
+    /// Put a value into target bits, other bits become 0.
+    #[inline]
+    fn try_put(&self, val: MaskedVal<T, R>) -> Result<(), Error<Self::BusError>> {
+        self.try_write(val.val)
+    }
+
+    /// Set target bits to 1, other bits become 0.
+    #[inline]
+    fn try_set(&self, bits: Bits<T, R>) -> Result<(), Error<Self::BusError>> {
+        self.try_write(bits.mask)
+    }
+
+    /// Set all bits to 1.
+    #[inline]
+    fn try_set_all(&self) -> Result<(), Error<Self::BusError>> {
+        self.try_write(T::all())
+    }
+
+    /// Set all bits to 0.
+    #[inline]
+    fn try_clear_all(&self) -> Result<(), Error<Self::BusError>> {
+        self.try_write(T::zero())
+    }
+}
+
+/// ## Fallible Read-Writable IO trait
+pub trait TryReadWritableIO<T: UIntLike, R: RegName = ()>: TryIO {
+    fn try_put_back(&self, val: MaskedVal<T, R>) -> Result<(), Error<Self::BusError>>;
+    fn try_set_back(&self, bits: Bits<T, R>) -> Result<(), Error<Self::BusError>>;
+    fn try_clear(&self, bits: Bits<T, R>) -> Result<(), Error<Self::BusError>>;
+    fn try_write_verified(&self, val: T) -> Result<(), Error<Self::BusError>>;
+    fn try_transaction<F: FnOnce(&mut Transaction<T, R>)>(&self, f: F) -> Result<(), Error<Self::BusError>>;
+}
+
+impl<U, T: UIntLike, R: RegName> TryReadWritableIO<T, R> for U
+where
+    U: TryReadableIO<T, R> + TryWritableIO<T, R>
+{
+    /// Put the value into target bits, while keeping others untouched.
+    #[inline]
+    fn try_put_back(&self, val: MaskedVal<T, R>) -> Result<(), Error<Self::BusError>> {
+        rmw(|| self.try_write(self.try_read()? & (!val.mask) | val.val))
+    }
+
+    /// Set target bits, while keeping others untouched.
+    #[inline]
+    fn try_set_back(&self, bits: Bits<T, R>) -> Result<(), Error<Self::BusError>> {
+        rmw(|| self.try_write(self.try_read()? | bits.mask))
+    }
+
+    /// Set target bits to 0, while keeping others untouched.
+    #[inline]
+    fn try_clear(&self, bits: Bits<T, R>) -> Result<(), Error<Self::BusError>> {
+        rmw(|| self.try_write(self.try_read()? & (!bits.mask)))
+    }
+
+    /// Write a value and read it back, failing with `Error::VerifyMismatch` if the
+    /// register doesn't hold it.
+    fn try_write_verified(&self, val: T) -> Result<(), Error<Self::BusError>> {
+        self.try_write(val)?;
+        let read = self.try_read()?;
+        if read == val {
+            Ok(())
+        } else {
+            Err(Error::VerifyMismatch {
+                written: val.to_u64(),
+                read: read.to_u64()
+            })
+        }
+    }
+
+    /// Accumulate the operations done in `f`, then apply them all with a single
+    /// read and a single write. Nothing is accessed if no bit is touched.
+    #[inline]
+    fn try_transaction<F: FnOnce(&mut Transaction<T, R>)>(&self, f: F) -> Result<(), Error<Self::BusError>> {
+        let mut tx = Transaction::new();
+        f(&mut tx);
+        if tx.is_empty() {
+            return Ok(());
+        }
+        rmw(|| self.try_write(tx.apply(self.try_read()?)))
+    }
+}
+
+impl<U: InfallibleIO> TryIO for U {
+    type BusError = Infallible;
+}
+
+impl<U, T: UIntLike, R: RegName> TryReadableIO<T, R> for U
+where
+    U: ReadableIO<T, R> + InfallibleIO
+{
+    #[inline]
+    fn try_read(&self) -> Result<T, Error<Infallible>> {
+        Ok(self.read())
+    }
+}
+
+impl<U, T: UIntLike, R: RegName> TryWritableIO<T, R> for U
+where
+    U: WritableIO<T, R> + InfallibleIO
+{
+    #[inline]
+    fn try_write(&self, val: T) -> Result<(), Error<Infallible>> {
+        self.write(val);
+        Ok(())
+    }
+}
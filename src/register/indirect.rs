@@ -0,0 +1,185 @@
+//! ## crate::register::indirect
+//!
+//! This mod contains indirect register access, for devices exposing their register
+//! file through an INDEX (or bank-select) register and a DATA register. Declare the
+//! indirect register map with `indirect_registers!{}`, then build an `IndirectSpace`
+//! on top of the two registers of an existing layout.
+//!
+//! Every access writes the register address to INDEX, then accesses DATA. The pair
+//! runs inside a `CriticalSection`, so it can't be interleaved with another access
+//! to the same space.
+//!
+//! Every index of the map must fit INDEX, and every register must be as wide as
+//! DATA, which is checked at compile time:
+//!
+//! ```compile_fail
+//! use re_reg::prelude::*;
+//! registers_layout! {
+//!     Regs {
+//!         ( 0x00 => INDEX: WO<u8> ),
+//!         ( 0x02 => DATA: RW<u16> ),
+//!         @END
+//!     }
+//! }
+//! indirect_registers! {
+//!     WideRegs {
+//!         ( 0x00 => CTRL: RW<u32> ),
+//!     }
+//! }
+//! let io = FakeDevice::<Regs>::new();
+//! let space = IndirectSpace::<_, _, WideRegs>::new(&io.INDEX, &io.DATA);
+//! // CTRL is wider than DATA.
+//! let _ = space.reg(WideRegs::CTRL).read();
+//! ```
+
+use core::marker::PhantomData;
+use core::mem::size_of;
+
+use crate::int::UIntLike;
+
+use super::backend::RegDesc;
+use super::bitfield::{ReadableIO, WritableIO};
+use super::fallible::InfallibleIO;
+use super::lock::{self, CriticalSection, NoLock};
+use super::{InnerRegister, Readable, Writable};
+
+/// ## Indirect register map
+///
+/// Implemented by register maps generated by `indirect_registers!{}`.
+pub trait IndirectMap {
+    /// The largest register index.
+    const MAX_INDEX: usize;
+}
+
+/// Whether index `max` fits an INDEX register of type `T`.
+const fn index_fits<T>(max: usize) -> bool {
+    size_of::<T>() >= size_of::<usize>() || max < 1 << (8 * size_of::<T>())
+}
+
+/// ## Indirect register space
+///
+/// The register map `L` reached through `index` and `data`, guarded by `C`.
+pub struct IndirectSpace<'a, I, D, L, C = NoLock>
+where
+    I: InnerRegister,
+    D: InnerRegister,
+    C: CriticalSection
+{
+    index: &'a I,
+    data: &'a D,
+    lock: C,
+    _layout: PhantomData<fn() -> L>
+}
+
+impl<'a, I, D, L> IndirectSpace<'a, I, D, L, NoLock>
+where
+    I: InnerRegister + WritableIO<I::Int, I::Name>,
+    D: InnerRegister
+{
+    pub fn new(index: &'a I, data: &'a D) -> Self {
+        Self {
+            index,
+            data,
+            lock: NoLock,
+            _layout: PhantomData
+        }
+    }
+}
+
+impl<'a, I, D, L, C> IndirectSpace<'a, I, D, L, C>
+where
+    I: InnerRegister + WritableIO<I::Int, I::Name>,
+    D: InnerRegister,
+    C: CriticalSection
+{
+    /// Guard every INDEX + DATA sequence with `lock`.
+    pub fn with_lock<C2: CriticalSection>(self, lock: C2) -> IndirectSpace<'a, I, D, L, C2> {
+        IndirectSpace {
+            index: self.index,
+            data: self.data,
+            lock,
+            _layout: PhantomData
+        }
+    }
+
+    /// Get the register described by `desc`.
+    #[inline]
+    pub fn reg<K: InnerRegister>(&self, desc: RegDesc<L, K>) -> IndirectRegister<'_, 'a, I, D, L, C, K>
+    where
+        L: IndirectMap
+    {
+        const {
+            assert!(index_fits::<I::Int>(L::MAX_INDEX), "Indirect register indices don't fit the INDEX register");
+            assert!(size_of::<K::Int>() == size_of::<D::Int>(), "Indirect register is not as wide as the DATA register");
+        };
+        IndirectRegister {
+            space: self,
+            addr: desc.offset(),
+            _reg: PhantomData
+        }
+    }
+
+    #[inline]
+    fn select(&self, addr: usize) {
+        self.index.write(I::Int::from_u64(addr as u64));
+    }
+}
+
+/// ## Indirect register
+///
+/// A register of an `IndirectSpace`. It implements the same IO traits as the
+/// `InnerRegister` kind `K` it stands for.
+pub struct IndirectRegister<'s, 'a, I, D, L, C, K>
+where
+    I: InnerRegister,
+    D: InnerRegister,
+    C: CriticalSection,
+    K: InnerRegister
+{
+    space: &'s IndirectSpace<'a, I, D, L, C>,
+    addr: usize,
+    _reg: PhantomData<fn() -> K>
+}
+
+impl<I, D, L, C, K> InfallibleIO for IndirectRegister<'_, '_, I, D, L, C, K>
+where
+    I: InnerRegister,
+    D: InnerRegister,
+    C: CriticalSection,
+    K: InnerRegister
+{}
+
+impl<I, D, L, C, K> ReadableIO<K::Int, K::Name> for IndirectRegister<'_, '_, I, D, L, C, K>
+where
+    I: InnerRegister + WritableIO<I::Int, I::Name>,
+    D: InnerRegister + ReadableIO<D::Int, D::Name>,
+    C: CriticalSection,
+    K: InnerRegister + Readable
+{
+    #[inline]
+    fn read(&self) -> K::Int {
+        let space = self.space;
+        let val = lock::run(&space.lock, || {
+            space.select(self.addr);
+            space.data.read()
+        });
+        K::Int::from_u64(val.to_u64())
+    }
+}
+
+impl<I, D, L, C, K> WritableIO<K::Int, K::Name> for IndirectRegister<'_, '_, I, D, L, C, K>
+where
+    I: InnerRegister + WritableIO<I::Int, I::Name>,
+    D: InnerRegister + WritableIO<D::Int, D::Name>,
+    C: CriticalSection,
+    K: InnerRegister + Writable
+{
+    #[inline]
+    fn write(&self, val: K::Int) {
+        let space = self.space;
+        lock::run(&space.lock, || {
+            space.select(self.addr);
+            space.data.write(D::Int::from_u64(val.to_u64()));
+        });
+    }
+}
@@ -0,0 +1,267 @@
+//! ## crate::register::lock
+//!
+//! This mod contains the `CriticalSection` hook, used where a sequence of register
+//! accesses must not be interleaved with accesses from another context (an ISR,
+//! another core, another thread).
+//!
+//! Implement it with whatever the platform offers, e.g. masking interrupts on a
+//! single core MCU. `MutexLock` is a `std::sync::Mutex` based one for user-space
+//! drivers.
+//!
+//! The read-modify-write methods of `ReadWritableIO` (`put_back()`, `set_back()`,
+//! `clear()` and `transaction()`) are not atomic, so an ISR writing the register
+//! between the read and the write loses its update. There are two ways to protect
+//! them:
+//! - Per register, `reg.locked(&cs)` returns a `Locked` handle whose read-modify-write
+//!   methods run inside `cs`.
+//! - Globally, with the `locked-rmw` feature, every read-modify-write the crate does
+//!   runs inside `GlobalLock`, whose hook is set once with `set_global_lock()`. This
+//!   covers `ReadWritableIO`, `TryReadWritableIO`, layout transactions, lane puts,
+//!   bank selection and shadowed registers.
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use crate::int::UIntLike;
+
+use super::RegName;
+use super::bitfield::{Bits, MaskedVal, ReadableIO, WritableIO};
+use super::transaction::Transaction;
+
+/// ## Critical section
+///
+/// The trait takes a `dyn FnMut` so it stays object-safe, use `run()` to get a
+/// return value out of the section.
+pub trait CriticalSection {
+    /// Run `f` inside the critical section.
+    fn with_lock(&self, f: &mut dyn FnMut());
+}
+
+/// A critical section doing nothing, for registers only accessed from one context.
+#[derive(Clone, Copy, Default)]
+pub struct NoLock;
+
+impl CriticalSection for NoLock {
+    #[inline]
+    fn with_lock(&self, f: &mut dyn FnMut()) {
+        f();
+    }
+}
+
+/// Run `f` inside the critical section `cs` and return its result.
+#[inline]
+pub fn run<C, F, V>(cs: &C, f: F) -> V
+where
+    C: CriticalSection + ?Sized,
+    F: FnOnce() -> V
+{
+    let mut f = Some(f);
+    let mut out = None;
+    cs.with_lock(&mut || {
+        if let Some(f) = f.take() {
+            out = Some(f());
+        }
+    });
+    out.expect("CriticalSection::with_lock() didn't run the closure")
+}
+
+/// ## Mutex critical section
+///
+/// For user-space drivers sharing registers between threads.
+#[cfg(feature = "std")]
+#[derive(Default)]
+pub struct MutexLock(std::sync::Mutex<()>);
+
+#[cfg(feature = "std")]
+impl MutexLock {
+    pub const fn new() -> Self {
+        Self(std::sync::Mutex::new(()))
+    }
+}
+
+#[cfg(feature = "std")]
+impl CriticalSection for MutexLock {
+    #[inline]
+    fn with_lock(&self, f: &mut dyn FnMut()) {
+        // A panicking holder can't leave a register half-written, so the lock is
+        // still good to use.
+        let _guard = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        f();
+    }
+}
+
+/// A hook running a section, see `set_global_lock()`.
+pub type LockHook = fn(&mut dyn FnMut());
+
+const UNSET: u8 = 0;
+const SETTING: u8 = 1;
+const SET: u8 = 2;
+
+/// The hook of `GlobalLock`, written once.
+struct GlobalHook {
+    state: AtomicU8,
+    hook: UnsafeCell<Option<LockHook>>
+}
+
+// SAFETY: `hook` is only written by the single `set()` moving `state` from `UNSET`
+// to `SETTING`, and only read once `state` is `SET`.
+unsafe impl Sync for GlobalHook {}
+
+impl GlobalHook {
+    fn set(&self, hook: LockHook) -> Result<(), LockHook> {
+        if self.state.compare_exchange(UNSET, SETTING, Ordering::Acquire, Ordering::Relaxed).is_err() {
+            return Err(hook);
+        }
+        // SAFETY: only this call got `SETTING`, and nobody reads before `SET`.
+        unsafe {
+            *self.hook.get() = Some(hook);
+        }
+        self.state.store(SET, Ordering::Release);
+        Ok(())
+    }
+
+    fn get(&self) -> Option<LockHook> {
+        if self.state.load(Ordering::Acquire) == SET {
+            // SAFETY: `hook` is never written again once `state` is `SET`.
+            unsafe { *self.hook.get() }
+        } else {
+            None
+        }
+    }
+}
+
+static GLOBAL_LOCK: GlobalHook = GlobalHook {
+    state: AtomicU8::new(UNSET),
+    hook: UnsafeCell::new(None)
+};
+
+/// Set the hook `GlobalLock` runs its sections with. It can only be set once, later
+/// calls give their hook back.
+pub fn set_global_lock(hook: LockHook) -> Result<(), LockHook> {
+    GLOBAL_LOCK.set(hook)
+}
+
+/// ## Global critical section
+///
+/// Runs sections with the hook set by `set_global_lock()`, or directly if none is
+/// set. With the `locked-rmw` feature, `ReadWritableIO` runs its read-modify-writes
+/// inside it.
+#[derive(Clone, Copy, Default)]
+pub struct GlobalLock;
+
+impl CriticalSection for GlobalLock {
+    #[inline]
+    fn with_lock(&self, f: &mut dyn FnMut()) {
+        match GLOBAL_LOCK.get() {
+            Some(hook) => hook(f),
+            None => f()
+        }
+    }
+}
+
+/// Run a read-modify-write, inside `GlobalLock` with the `locked-rmw` feature.
+#[inline]
+pub(crate) fn rmw<V>(f: impl FnOnce() -> V) -> V {
+    if cfg!(feature = "locked-rmw") {
+        run(&GlobalLock, f)
+    } else {
+        f()
+    }
+}
+
+/// ## Locked register
+///
+/// A register whose read-modify-write methods run inside critical section `C`, get
+/// it with `ReadWritableIO::locked()`. Plain reads and writes are single accesses and
+/// need no lock, use `reg()` for writes.
+pub struct Locked<'a, U, C: CriticalSection + ?Sized> {
+    reg: &'a U,
+    cs: &'a C
+}
+
+impl<'a, U, C: CriticalSection + ?Sized> Locked<'a, U, C> {
+    #[inline]
+    pub(crate) fn new(reg: &'a U, cs: &'a C) -> Self {
+        Self { reg, cs }
+    }
+
+    /// The register itself.
+    #[inline]
+    pub fn reg(&self) -> &'a U {
+        self.reg
+    }
+
+    /// Put the value into target bits, while keeping others untouched.
+    #[inline]
+    pub fn put_back<T, R>(&self, val: MaskedVal<T, R>)
+    where
+        U: ReadableIO<T, R> + WritableIO<T, R>,
+        T: UIntLike,
+        R: RegName
+    {
+        run(self.cs, || self.reg.write(self.reg.read() & (!val.mask) | val.val));
+    }
+
+    /// Set target bits, while keeping others untouched.
+    #[inline]
+    pub fn set_back<T, R>(&self, bits: Bits<T, R>)
+    where
+        U: ReadableIO<T, R> + WritableIO<T, R>,
+        T: UIntLike,
+        R: RegName
+    {
+        run(self.cs, || self.reg.write(self.reg.read() | bits.mask));
+    }
+
+    /// Set target bits to 0, while keeping others untouched.
+    #[inline]
+    pub fn clear<T, R>(&self, bits: Bits<T, R>)
+    where
+        U: ReadableIO<T, R> + WritableIO<T, R>,
+        T: UIntLike,
+        R: RegName
+    {
+        run(self.cs, || self.reg.write(self.reg.read() & (!bits.mask)));
+    }
+
+    /// Flip target bits, while keeping others untouched.
+    #[inline]
+    pub fn toggle<T, R>(&self, bits: Bits<T, R>)
+    where
+        U: ReadableIO<T, R> + WritableIO<T, R>,
+        T: UIntLike,
+        R: RegName
+    {
+        run(self.cs, || self.reg.write(self.reg.read() ^ bits.mask));
+    }
+
+    /// Accumulate the operations done in `f` outside the section, then apply them
+    /// with a single read and a single write inside it.
+    #[inline]
+    pub fn transaction<T, R, F>(&self, f: F)
+    where
+        U: ReadableIO<T, R> + WritableIO<T, R>,
+        T: UIntLike,
+        R: RegName,
+        F: FnOnce(&mut Transaction<T, R>)
+    {
+        let mut tx = Transaction::new();
+        f(&mut tx);
+        if !tx.is_empty() {
+            run(self.cs, || self.reg.write(tx.apply(self.reg.read())));
+        }
+    }
+}
+
+impl<U, C, T, R> ReadableIO<T, R> for Locked<'_, U, C>
+where
+    U: ReadableIO<T, R>,
+    C: CriticalSection + ?Sized,
+    T: UIntLike,
+    R: RegName
+{
+    #[inline]
+    fn read(&self) -> T {
+        self.reg.read()
+    }
+}
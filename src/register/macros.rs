@@ -12,9 +12,38 @@
 /// `FR::Reg` can be used only on that register. Please refer to `register::bitfield` mod
 /// to view details.
 ///
+/// Besides the struct, a `RegDesc` constant is generated for each register, e.g.
+/// `MyDeviceRegs::DR`. Use it with `Device::reg()` to access the register through
+/// any `RegisterBackend` instead of casting the layout onto memory.
+///
+/// Registers of a foreign byte order use the `_BE`/`_LE` kinds, e.g. `RW_BE<u32, CR::Reg>`,
+/// and a layout can set the default byte order of its plain kinds with `Name(BE) { ... }`
+/// or `Name(LE) { ... }`. Values and `Bits` offsets stay in logical bit order, see
+/// `register::endian` mod.
+///
+/// Registers wider than the bus use the `SPLIT<u64, u32, HighFirst, R>` kind, giving the
+/// full width, the access width and the order of the halves, see `register::split` mod.
+///
+/// Registers in memory shared between cores use the `ATOMIC<u32, R>` kind, whose
+/// read-modify-writes are atomic, see `register::atomic` mod.
+///
+/// Registers with hardware SET/CLR/TOG aliases at +0x4/+0x8/+0xc use the `SCT<u32, R>`
+/// kind, whose `set_back()`, `clear()` and `toggle()` are single alias writes, see
+/// `register::alias` mod.
+///
+/// Every register's declared offset is checked against its place in the struct at
+/// compile time, which also catches registers declared inside another's alias slots.
+///
+/// Banked registers are declared after `@END` with `@BANKS SEL[FIELD] { ... }`, where
+/// `SEL` is the bank-select register and `FIELD` its selector bit field. Each bank is
+/// a marker type, a selector value and a layout overlaid on the same base. Accessing
+/// `dev.bank::<Bank1>()` selects the bank and returns a guard exposing its registers,
+/// it borrows `dev` mutably so only one bank is selected at a time, see
+/// `register::bank` mod.
+///
 /// ### Example
 /// ```
-/// use neo_reg::prelude::*;
+/// use re_reg::prelude::*;
 /// reg_bitfields! {
 ///     FR(u32) [ FR1 [ 0 => 8 ] ],
 ///     DR(u32) [ DR1 [ 0 => 8 ] ]
@@ -26,31 +55,32 @@
 ///         /* Every layout should start from 0x00 */
 ///         ( 0x00 => FR: RO<u32, FR::Reg> ),
 ///         ( 0x04 => DR: RW<u32, DR::Reg> ),
-///         /* Padding field, from 0x08 to 0x0f is marked reserved */
-///         ( 0x08 => _reserved0 ),
+///         /* Read-to-clear register, read it with `take()` */
+///         ( 0x08 => ISR: RC<u32> ),
+///         /* Padding field, from 0x0c to 0x0f is marked reserved */
+///         ( 0x0c => _reserved0 ),
 ///         /* Omit the bitfield is okay for regs that don't need bit-field operations */
 ///         ( 0x10 => CR: WO<u32> ),
+///         /* Write-only register with a software shadow copy */
+///         ( 0x14 => IMR: WOS<u32> ),
 ///         /* @END indicates the layout ends here */
 ///         @END
-///         /* The range it represents is from 0x00 to 0x13 */
+///         /* The range it represents is from 0x00 to 0x17 */
 ///     }
 /// }
 /// ```
 ///
-/// TODO:
-/// - Add compile time check for parameter validity.
-///
 #[macro_export]
 macro_rules! registers_layout {
     {
         $(#[$attr:meta])*
-        $name:ident {
+        $name:ident$(($endian:ident))? {
             $($field:tt)*
         }
     } => {
         $crate::reg_fields!(
             $(#[$attr])*
-            $name {
+            $name$(($endian))? {
                 $($field)*
             }
         );
@@ -59,18 +89,49 @@ macro_rules! registers_layout {
 
 #[macro_export]
 macro_rules! reg_fields {
+    /* Mapping a byte order to its type. */
+    (@endian) => { $crate::prelude::NativeEndian };
+    (@endian BE) => { $crate::prelude::BigEndian };
+    (@endian LE) => { $crate::prelude::LittleEndian };
+    /* Mapping a register kind to its type, `$end` is the layout's byte order. */
+    (@kind [$end:ty] RO<$typ:ty$(, $rname:path)?>) => { $crate::reg_fields!(@inner ROInnerRegister $end, $typ$(, $rname)?) };
+    (@kind [$end:ty] RC<$typ:ty$(, $rname:path)?>) => { $crate::reg_fields!(@inner RCInnerRegister $end, $typ$(, $rname)?) };
+    (@kind [$end:ty] WO<$typ:ty$(, $rname:path)?>) => { $crate::reg_fields!(@inner WOInnerRegister $end, $typ$(, $rname)?) };
+    (@kind [$end:ty] WOS<$typ:ty$(, $rname:path)?>) => { $crate::reg_fields!(@inner WOSInnerRegister $end, $typ$(, $rname)?) };
+    (@kind [$end:ty] RW<$typ:ty$(, $rname:path)?>) => { $crate::reg_fields!(@inner RWInnerRegister $end, $typ$(, $rname)?) };
+    (@kind [$end:ty] SCT<$typ:ty$(, $rname:path)?>) => { $crate::reg_fields!(@inner AliasedInnerRegister $end, $typ$(, $rname)?) };
+    (@kind [$end:ty] RO_BE<$typ:ty$(, $rname:path)?>) => { $crate::reg_fields!(@kind [$crate::prelude::BigEndian] RO<$typ$(, $rname)?>) };
+    (@kind [$end:ty] RC_BE<$typ:ty$(, $rname:path)?>) => { $crate::reg_fields!(@kind [$crate::prelude::BigEndian] RC<$typ$(, $rname)?>) };
+    (@kind [$end:ty] WO_BE<$typ:ty$(, $rname:path)?>) => { $crate::reg_fields!(@kind [$crate::prelude::BigEndian] WO<$typ$(, $rname)?>) };
+    (@kind [$end:ty] WOS_BE<$typ:ty$(, $rname:path)?>) => { $crate::reg_fields!(@kind [$crate::prelude::BigEndian] WOS<$typ$(, $rname)?>) };
+    (@kind [$end:ty] RW_BE<$typ:ty$(, $rname:path)?>) => { $crate::reg_fields!(@kind [$crate::prelude::BigEndian] RW<$typ$(, $rname)?>) };
+    (@kind [$end:ty] RO_LE<$typ:ty$(, $rname:path)?>) => { $crate::reg_fields!(@kind [$crate::prelude::LittleEndian] RO<$typ$(, $rname)?>) };
+    (@kind [$end:ty] RC_LE<$typ:ty$(, $rname:path)?>) => { $crate::reg_fields!(@kind [$crate::prelude::LittleEndian] RC<$typ$(, $rname)?>) };
+    (@kind [$end:ty] WO_LE<$typ:ty$(, $rname:path)?>) => { $crate::reg_fields!(@kind [$crate::prelude::LittleEndian] WO<$typ$(, $rname)?>) };
+    (@kind [$end:ty] WOS_LE<$typ:ty$(, $rname:path)?>) => { $crate::reg_fields!(@kind [$crate::prelude::LittleEndian] WOS<$typ$(, $rname)?>) };
+    (@kind [$end:ty] RW_LE<$typ:ty$(, $rname:path)?>) => { $crate::reg_fields!(@kind [$crate::prelude::LittleEndian] RW<$typ$(, $rname)?>) };
+    (@kind [$end:ty] SPLIT<$typ:ty, $half:ty, $order:ident$(, $rname:path)?>) => {
+        $crate::prelude::SplitInnerRegister<$typ, $half, $crate::prelude::$order, $crate::reg_fields!(@name $($rname)?), $end>
+    };
+    (@kind [$end:ty] ATOMIC<$typ:ty$(, $rname:path)?>) => {
+        $crate::prelude::AtomicInnerRegister<$typ, $crate::reg_fields!(@name $($rname)?)>
+    };
+    (@name) => { () };
+    (@name $rname:path) => { $rname };
+    (@inner $reg:ident $end:ty, $typ:ty) => { $crate::prelude::$reg<$typ, (), $end> };
+    (@inner $reg:ident $end:ty, $typ:ty, $rname:path) => { $crate::prelude::$reg<$typ, $rname, $end> };
     /* Parsing struct header. */
     (
         $(#[$attr:meta])*
-        $name:ident {
+        $name:ident$(($endian:ident))? {
             $($fieled:tt)*
         }
     ) => {
         $crate::reg_fields!(
-            ( $($fieled)* ) -> {
+            ( $($fieled)* ) -> [$crate::reg_fields!(@endian $($endian)?)] {
                 $(#[$attr])*
                 struct $name;
-            }
+            } {}
         );
     };
     /* Parsing paddings. */
@@ -79,57 +140,49 @@ macro_rules! reg_fields {
             ($offset:literal => $padding:ident),
             ($offset_next:literal => $($field_next:tt)*),
             $($other:tt)*
-        ) -> { $($out:tt)* }
+        ) -> [$end:ty] { $($out:tt)* } { $($desc:tt)* }
     ) => {
         $crate::reg_fields!(
             (
                 ($offset_next => $($field_next)*),
                 $($other)*
-            ) -> {
-                $($out)*
-                ($padding: [u8; $offset_next - $offset]),
-            }
-        );
-    };
-    /* Parsing read-only regs. */
-    (
-        (
-            ($offset:literal => $name:ident: RO<$typ:ty$(, $rname:path)?>),
-            $($other:tt)*
-        ) -> { $($out:tt)* }
-    ) => {
-        $crate::reg_fields!(
-            ( $($other)* ) -> {
+            ) -> [$end] {
                 $($out)*
-                ($name: ROInnerRegister<$typ$(, $rname)?>),
-            }
+                ($padding: [core::cell::UnsafeCell<u8>; $offset_next - $offset]),
+            } { $($desc)* }
         );
     };
-    /* Parsing write-only regs. */
+    /* Parsing split regs. */
     (
         (
-            ($offset:literal => $name:ident: WO<$typ:ty$(, $rname:path)?>),
+            ($offset:literal => $name:ident: SPLIT<$typ:ty, $half:ty, $order:ident$(, $rname:path)?>),
             $($other:tt)*
-        ) -> { $($out:tt)* }
+        ) -> [$end:ty] { $($out:tt)* } { $($desc:tt)* }
     ) => {
         $crate::reg_fields!(
-            ( $($other)* ) -> {
+            ( $($other)* ) -> [$end] {
                 $($out)*
-                ($name: WOInnerRegister<$typ$(, $rname)?>),
+                ($name: $crate::reg_fields!(@kind [$end] SPLIT<$typ, $half, $order$(, $rname)?>)),
+            } {
+                $($desc)*
+                ($name: $crate::reg_fields!(@kind [$end] SPLIT<$typ, $half, $order$(, $rname)?>) => $offset),
             }
         );
     };
-    /* Parsing read-write regs. */
+    /* Parsing regs. */
     (
         (
-            ($offset:expr => $name:ident: RW<$typ:ty$(, $rname:path)?>),
+            ($offset:literal => $name:ident: $kind:ident<$typ:ty$(, $rname:path)?>),
             $($other:tt)*
-        ) -> { $($out:tt)* }
+        ) -> [$end:ty] { $($out:tt)* } { $($desc:tt)* }
     ) => {
         $crate::reg_fields!(
-            ( $($other)* ) -> {
+            ( $($other)* ) -> [$end] {
                 $($out)*
-                ($name: RWInnerRegister<$typ$(, $rname)?>),
+                ($name: $crate::reg_fields!(@kind [$end] $kind<$typ$(, $rname)?>)),
+            } {
+                $($desc)*
+                ($name: $crate::reg_fields!(@kind [$end] $kind<$typ$(, $rname)?>) => $offset),
             }
         );
     };
@@ -137,12 +190,19 @@ macro_rules! reg_fields {
     (
         (
             @END$(,)?
-        ) -> {
+            $(@BANKS $sel:ident[$sel_field:path] {
+                $($bank:ident = $bank_val:literal => $bank_layout:ty),*$(,)?
+            }$(,)?)?
+        ) -> [$end:ty] {
             $(#[$attr:meta])*
             struct $struct_name:ident;
             $(
                 ($entry_name:ident: $typ:ty),
             )*
+        } {
+            $(
+                ($reg_name:ident: $reg_typ:ty => $reg_offset:expr),
+            )*
         }
     ) => {
         $(#[$attr])*
@@ -153,6 +213,243 @@ macro_rules! reg_fields {
                 pub $entry_name: $typ
             ),*
         }
+
+        // SAFETY: registers and paddings are all `UnsafeCell`s of integers, or
+        // atomic integers.
+        unsafe impl $crate::prelude::RegisterLayout for $struct_name {
+            fn reg_info(offset: usize) -> Option<$crate::prelude::RegInfo> {
+                $(
+                    if offset == $reg_offset {
+                        return Some($crate::prelude::RegInfo::of::<$reg_typ>(stringify!($reg_name)));
+                    }
+                )*
+                None
+            }
+
+            fn reg_ref<I: 'static>(&self, offset: usize) -> Option<&I> {
+                $(
+                    if offset == $reg_offset {
+                        return (&self.$reg_name as &dyn core::any::Any).downcast_ref::<I>();
+                    }
+                )*
+                None
+            }
+        }
+
+        const _: () = {
+            $(
+                assert!(
+                    core::mem::offset_of!($struct_name, $reg_name) == $reg_offset,
+                    concat!("Register ", stringify!($reg_name), " is not at its declared offset")
+                );
+            )*
+        };
+
+        #[allow(non_upper_case_globals)]
+        impl $struct_name {
+            $(
+                // SAFETY: the register's offset is checked above.
+                pub const $reg_name: $crate::prelude::RegDesc<$struct_name, $reg_typ> =
+                    unsafe { $crate::prelude::RegDesc::new($reg_offset) };
+            )*
+
+            /// Accumulate the operations done in `f` on this layout's registers, then
+            /// apply them with a single read and a single write per touched register.
+            #[inline]
+            pub fn transaction<'a, F>(&'a self, f: F)
+            where
+                F: FnOnce(&mut $crate::prelude::LayoutTransaction<'a, $struct_name, { [$(stringify!($reg_name)),*].len() }>)
+            {
+                $crate::__run_layout_transaction(self, f);
+            }
+        }
+
+        $(
+        impl $crate::prelude::BankSelect for $struct_name {}
+
+        $(
+            pub struct $bank;
+
+            // SAFETY: the size and alignment of the bank layout are checked below.
+            unsafe impl $crate::prelude::Bank for $bank {
+                type Owner = $struct_name;
+                type Layout = $bank_layout;
+
+                #[inline]
+                fn select(owner: &$struct_name) -> u64 {
+                    $crate::prelude::UIntLike::to_u64(
+                        $crate::__select_bank_field(&owner.$sel, $sel_field, $bank_val)
+                    )
+                }
+
+                #[inline]
+                fn restore(owner: &$struct_name, prev: u64) {
+                    $crate::prelude::ReadWritableIO::put_back(
+                        &owner.$sel,
+                        $sel_field.val($crate::prelude::UIntLike::from_u64(prev))
+                    );
+                }
+            }
+
+            const _: fn() = || {
+                fn is_layout<T: $crate::prelude::RegisterLayout>() {}
+                is_layout::<$bank_layout>();
+            };
+
+            const _: () = assert!(
+                core::mem::size_of::<$bank_layout>() <= core::mem::size_of::<$struct_name>(),
+                "A bank layout must not be bigger than its owner layout"
+            );
+
+            const _: () = assert!(
+                core::mem::align_of::<$bank_layout>() <= core::mem::align_of::<$struct_name>(),
+                "A bank layout must not be more aligned than its owner layout"
+            );
+        )*)?
+    };
+}
+
+/// This macro is used to generate a register map of a device behind a bus (I2C, SPI
+/// and so on). Unlike `registers_layout!{}`, it doesn't generate a struct with
+/// register fields. It generates a marker struct with a `RegDesc` constant for each
+/// register, whose offset is the register address on the bus. Use these constants
+/// with `BusDevice::reg()`.
+///
+/// Supported register kinds are `RO`, `RC`, `WO` and `RW`. The register width comes
+/// from the register type, and the address width from `RegisterBus::Addr`.
+///
+/// Registers whose value may change by itself (status, data, counters, ...) should be
+/// marked `volatile`, so a `RegCache` never serves them from the cache. `RC` registers
+/// are always volatile.
+///
+/// ### Example
+/// ```
+/// use re_reg::prelude::*;
+/// reg_bitfields! {
+///     CTRL(u8) [ MODE [ 0 => 2 ], OSRS [ 2 => 3 ] ]
+/// }
+/// bus_registers! {
+///     /* Add attributes here. */
+///     SensorRegs {
+///         /* ( {address} => {name}: {kind}<{type}, {reg_name}> ) */
+///         ( 0xd0 => ID: RO<u8> ),
+///         ( 0xf4 => CTRL: RW<u8, CTRL::Reg> ),
+///         ( 0xfa => TEMP: RO<u16>, volatile ),
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! bus_registers {
+    {
+        $(#[$attr:meta])*
+        $name:ident {
+            $(( $addr:literal => $reg:ident: $kind:ident<$typ:ty$(, $rname:path)?>$(, $vol:ident)? )),*$(,)?
+        }
+    } => {
+        $crate::bus_registers!(@descs $(#[$attr])* $name {
+            $(( $addr => $reg: $kind<$typ$(, $rname)?> ))*
+        });
+
+        impl $crate::prelude::RegisterMap for $name {
+            const END: usize = {
+                let mut end = 0;
+                $(
+                    let reg_end = $addr + core::mem::size_of::<$typ>();
+                    if reg_end > end {
+                        end = reg_end;
+                    }
+                )*
+                end
+            };
+
+            fn is_volatile(addr: usize) -> bool {
+                match addr {
+                    $($addr => $crate::bus_registers!(@volatile $kind $($vol)?),)*
+                    _ => true
+                }
+            }
+
+            fn reg_at(addr: usize) -> Option<(usize, usize)> {
+                $(
+                    if addr.wrapping_sub($addr) < core::mem::size_of::<$typ>() {
+                        return Some(($addr, core::mem::size_of::<$typ>()));
+                    }
+                )*
+                None
+            }
+        }
+    };
+    (@descs $(#[$attr:meta])* $name:ident {
+        $(( $addr:literal => $reg:ident: $kind:ident<$typ:ty$(, $rname:path)?> ))*
+    }) => {
+        $(#[$attr])*
+        pub struct $name;
+
+        #[allow(non_upper_case_globals)]
+        impl $name {
+            $(
+                // SAFETY: the map holds this register at this address.
+                pub const $reg: $crate::prelude::RegDesc<
+                    $name,
+                    $crate::bus_registers!(@kind $kind<$typ$(, $rname)?>)
+                > = unsafe { $crate::prelude::RegDesc::new($addr) };
+            )*
+        }
+    };
+    (@volatile RC $($vol:ident)?) => { true };
+    (@volatile $kind:ident) => { false };
+    (@volatile $kind:ident volatile) => { true };
+    (@kind RO<$typ:ty$(, $rname:path)?>) => { $crate::prelude::ROInnerRegister<$typ$(, $rname)?> };
+    (@kind RC<$typ:ty$(, $rname:path)?>) => { $crate::prelude::RCInnerRegister<$typ$(, $rname)?> };
+    (@kind WO<$typ:ty$(, $rname:path)?>) => { $crate::prelude::WOInnerRegister<$typ$(, $rname)?> };
+    (@kind RW<$typ:ty$(, $rname:path)?>) => { $crate::prelude::RWInnerRegister<$typ$(, $rname)?> };
+}
+
+/// This macro is used to generate an indirect register map, i.e. registers reached
+/// through an INDEX register and a DATA register of another layout. Like
+/// `bus_registers!{}`, it generates a marker struct with a `RegDesc` constant for
+/// each register, whose offset is the value written to INDEX. Use these constants
+/// with `IndirectSpace::reg()`.
+///
+/// Supported register kinds are `RO`, `WO` and `RW`.
+///
+/// ### Example
+/// ```
+/// use re_reg::prelude::*;
+/// reg_bitfields! {
+///     BMCR(u16) [ SPEED [ 13 => 1 ], RESET [ 15 => 1 ] ]
+/// }
+/// indirect_registers! {
+///     PhyRegs {
+///         /* ( {index} => {name}: {kind}<{type}, {reg_name}> ) */
+///         ( 0x00 => BMCR: RW<u16, BMCR::Reg> ),
+///         ( 0x02 => PHYID1: RO<u16> ),
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! indirect_registers {
+    {
+        $(#[$attr:meta])*
+        $name:ident {
+            $(( $index:literal => $reg:ident: $kind:ident<$typ:ty$(, $rname:path)?> )),*$(,)?
+        }
+    } => {
+        $crate::bus_registers!(@descs $(#[$attr])* $name {
+            $(( $index => $reg: $kind<$typ$(, $rname)?> ))*
+        });
+
+        impl $crate::prelude::IndirectMap for $name {
+            const MAX_INDEX: usize = {
+                let mut max = 0;
+                $(
+                    if $index > max {
+                        max = $index;
+                    }
+                )*
+                max
+            };
+        }
     };
 }
 
@@ -170,7 +467,7 @@ macro_rules! reg_fields {
 ///
 /// ### Example
 /// ```
-/// use neo_reg::prelude::*;
+/// use re_reg::prelude::*;
 /// reg_bitfields! {
 ///     CR(u32) [
 ///         /* [ offset => field_size] */
@@ -198,10 +495,21 @@ macro_rules! reg_bitfields {
         $(
             #[allow(non_snake_case)]
             #[allow(non_upper_case_globals)]
+            #[allow(clippy::int_plus_one)]
             pub mod $rname {
                 use $crate::prelude::*;
                 pub struct Reg(PhantomData<$typ>);
-                impl RegName for Reg {}
+                impl RegName for Reg {
+                    const NAME: &'static str = stringify!($rname);
+                    const FIELDS: &'static [FieldDesc] = &[$(
+                        FieldDesc {
+                            name: stringify!($name),
+                            offset: $offset,
+                            width: $size,
+                            values: &[$($((stringify!($vname), $vval as u64)),*)?]
+                        }
+                    ),*];
+                }
                 impl BitsLike<$typ> for Reg {}
                 $(
                     pub const $name: Bits<$typ, Reg> = {
@@ -218,6 +526,13 @@ macro_rules! reg_bitfields {
                             (((1 as $typ).wrapping_shl($size) & ((0 as $typ).wrapping_sub(2))).wrapping_sub(1) << $offset
                         )
                     )};
+                    #[allow(non_camel_case_types)]
+                    pub enum $name {}
+                    impl BitField for $name {
+                        type Reg = Reg;
+                        const OFFSET: usize = $offset;
+                        const WIDTH: usize = $size;
+                    }
                     $(
                         $(pub const $vname: $typ = $vval;)*
                     )?
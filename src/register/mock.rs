@@ -0,0 +1,225 @@
+//! ## crate::register::mock
+//!
+//! This mod contains `MockDevice`, a backend checking a driver against a list of
+//! expected accesses, declared with the bit fields from `reg_bitfields!{}`:
+//!
+//! ```
+//! use re_reg::prelude::*;
+//! reg_bitfields! {
+//!     CR(u32) [
+//!         EN [ 0 => 1 ],
+//!         DLEN [ 4 => 2 ] { DLen7 = 0b01, DLen8 = 0b10 }
+//!     ]
+//! }
+//! registers_layout! {
+//!     UartRegs {
+//!         ( 0x00 => CR: RW<u32, CR::Reg> ),
+//!         ( 0x04 => SR: RO<u32> ),
+//!         @END
+//!     }
+//! }
+//!
+//! let mock = MockDevice::<UartRegs>::new();
+//! mock.expect_write(UartRegs::CR, CR::EN + CR::DLEN.val(CR::DLen8))
+//!     .expect_read(UartRegs::SR, 0x20);
+//!
+//! let dev = Device::<_, UartRegs>::new(mock);
+//! dev.reg(UartRegs::CR).write(0x21);
+//! assert_eq!(dev.reg(UartRegs::SR).read(), 0x20);
+//! dev.backend().verify();
+//! ```
+//!
+//! Expectations are met in order. A write expectation only checks the bits given,
+//! other bits of the written value may be anything. The first access not matching
+//! the next expectation panics, naming the register and the fields that differ.
+//! Expectations not met panic in `verify()`, or when the mock is dropped.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt::{self, Write};
+
+use crate::int::UIntLike;
+
+use super::{FieldDesc, InnerRegister, RegName, RegisterLayout};
+use super::backend::{RegDesc, RegisterBackend};
+use super::bitfield::MaskedVal;
+use super::endian::Endianness;
+use super::trace::{Access, Direction};
+
+/// An expected access.
+struct Expectation {
+    direction: Direction,
+    offset: usize,
+    width: usize,
+    /// The value in logical bit order, written bits or returned value.
+    value: u64,
+    /// The bits of `value` a write must match.
+    mask: u64,
+    name: &'static str,
+    fields: &'static [FieldDesc],
+    from_hw: fn(u64) -> u64,
+    to_hw: fn(u64) -> u64
+}
+
+impl Expectation {
+    fn new<L: RegisterLayout, I: InnerRegister>(direction: Direction, offset: usize, value: u64, mask: u64) -> Self {
+        Self {
+            direction,
+            offset,
+            width: size_of::<I::Int>(),
+            value,
+            mask,
+            name: L::reg_info(offset).map_or(<I::Name as RegName>::NAME, |info| info.name),
+            fields: <I::Name as RegName>::FIELDS,
+            from_hw: |val| I::Endian::from_hw(I::Int::from_u64(val)).to_u64(),
+            to_hw: |val| I::Endian::to_hw(I::Int::from_u64(val)).to_u64()
+        }
+    }
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.direction {
+            Direction::Read => write!(f, "R {} @{:#x} returning {:#x}", self.name, self.offset, self.value),
+            Direction::Write => {
+                write!(f, "W {} @{:#x} with", self.name, self.offset)?;
+                let mut rest = self.mask;
+                for field in self.fields {
+                    let fmask = field.mask();
+                    if self.mask & fmask == fmask {
+                        write!(f, " {}={}", field.name, field.display(self.value))?;
+                        rest &= !fmask;
+                    }
+                }
+                if rest != 0 {
+                    write!(f, " bits {:#x}={:#x}", rest, self.value & rest)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// ## Mock device
+///
+/// Expectations are added through `&self`, so a test can add more once the mock
+/// is wrapped in a `Device`.
+pub struct MockDevice<L: RegisterLayout> {
+    expected: RefCell<VecDeque<Expectation>>,
+    _layout: core::marker::PhantomData<fn() -> L>
+}
+
+impl<L: RegisterLayout> Default for MockDevice<L> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<L: RegisterLayout> MockDevice<L> {
+    /// Create a mock expecting no access.
+    pub fn new() -> Self {
+        Self {
+            expected: RefCell::new(VecDeque::new()),
+            _layout: core::marker::PhantomData
+        }
+    }
+
+    /// Expect a write of the register described by `desc`, with the bits in `val`
+    /// set as given.
+    pub fn expect_write<I, V>(&self, desc: RegDesc<L, I>, val: V) -> &Self
+    where
+        I: InnerRegister,
+        V: Into<MaskedVal<I::Int, I::Name>>
+    {
+        let val = val.into();
+        self.expected.borrow_mut().push_back(Expectation::new::<L, I>(
+            Direction::Write, desc.offset(), val.val.to_u64(), val.mask.to_u64()
+        ));
+        self
+    }
+
+    /// Expect a write of exactly `val` to the register described by `desc`.
+    pub fn expect_write_exact<I: InnerRegister>(&self, desc: RegDesc<L, I>, val: I::Int) -> &Self {
+        self.expected.borrow_mut().push_back(Expectation::new::<L, I>(
+            Direction::Write, desc.offset(), val.to_u64(), I::Int::all().to_u64()
+        ));
+        self
+    }
+
+    /// Expect a read of the register described by `desc`, returning `val`.
+    pub fn expect_read<I: InnerRegister>(&self, desc: RegDesc<L, I>, val: I::Int) -> &Self {
+        self.expected.borrow_mut().push_back(Expectation::new::<L, I>(
+            Direction::Read, desc.offset(), val.to_u64(), 0
+        ));
+        self
+    }
+
+    /// Panic if some expectations are not met.
+    pub fn verify(&self) {
+        let expected = self.expected.borrow();
+        if let Some(next) = expected.front() {
+            panic!("expected access {} not met ({} expectations left)", next, expected.len());
+        }
+    }
+
+    fn access(&self, direction: Direction, offset: usize, width: usize, raw: u64) -> u64 {
+        let mut expected = self.expected.borrow_mut();
+        let Some(next) = expected.pop_front() else {
+            drop(expected);
+            panic!("unexpected access {}", self.decode(direction, offset, width, raw));
+        };
+        let value = (next.from_hw)(raw);
+        if next.direction != direction || next.offset != offset || next.width != width {
+            drop(expected);
+            panic!(
+                "unexpected access {}\n  expected {}",
+                self.decode(direction, offset, width, raw), next
+            );
+        }
+        if direction == Direction::Write && (value ^ next.value) & next.mask != 0 {
+            let mut msg = std::format!(
+                "unexpected value in {}\n  expected {}",
+                Access { name: next.name, offset, width, value, direction, fields: next.fields }, next
+            );
+            let mut rest = (value ^ next.value) & next.mask;
+            for field in next.fields {
+                let fmask = field.mask();
+                if rest & fmask != 0 {
+                    let _ = write!(
+                        msg, "\n  {}: expected {}, got {}",
+                        field.name, field.display(next.value), field.display(value)
+                    );
+                    rest &= !fmask;
+                }
+            }
+            if rest != 0 {
+                let _ = write!(msg, "\n  bits {:#x}: expected {:#x}, got {:#x}", rest, next.value & rest, value & rest);
+            }
+            drop(expected);
+            panic!("{}", msg);
+        }
+        (next.to_hw)(next.value)
+    }
+
+    fn decode(&self, direction: Direction, offset: usize, width: usize, raw: u64) -> Access {
+        Access::decode::<L>(direction, offset, width, raw)
+    }
+}
+
+impl<L: RegisterLayout> Drop for MockDevice<L> {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            self.verify();
+        }
+    }
+}
+
+impl<L: RegisterLayout> RegisterBackend for MockDevice<L> {
+    fn read<T: UIntLike>(&self, offset: usize) -> T {
+        T::from_u64(self.access(Direction::Read, offset, size_of::<T>(), 0))
+    }
+
+    fn write<T: UIntLike>(&self, offset: usize, val: T) {
+        self.access(Direction::Write, offset, size_of::<T>(), val.to_u64());
+    }
+}
@@ -0,0 +1,248 @@
+//! ## crate::register::replay
+//!
+//! This mod contains record-and-replay of register access sequences, for regression
+//! tests of init routines.
+//!
+//! Wrap the backend of a `Device` with `Recorder` and run the driver, then save
+//! `recorder.script()` as text. In the regression test, run the driver over a
+//! `Replayer` of the saved script: reads return the recorded values, and the first
+//! access diverging from the script (wrong register, wrong value, an extra access)
+//! panics with the expected and actual access decoded field by field. Missing
+//! accesses panic when the replayer is finished or dropped.
+//!
+//! The text format has one access per line: direction, offset, width in bytes and
+//! value, e.g. `W 0x04 4 0x00000021`. Empty lines and `#` comments are skipped.
+//! Values are recorded as the backend sees them, in hardware byte order, and are
+//! converted to the register's logical bit order when decoded.
+
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::format;
+use std::marker::PhantomData;
+use std::string::String;
+use std::vec::Vec;
+
+use crate::int::UIntLike;
+
+use super::RegisterLayout;
+use super::backend::RegisterBackend;
+use super::trace::{Access, Direction};
+
+/// ## Recorded access
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Event {
+    pub direction: Direction,
+    pub offset: usize,
+    pub width: usize,
+    pub value: u64
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dir = match self.direction {
+            Direction::Read => 'R',
+            Direction::Write => 'W'
+        };
+        write!(f, "{} {:#04x} {} {:#0w$x}", dir, self.offset, self.width, self.value, w = self.width * 2 + 2)
+    }
+}
+
+/// ## Access script
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct Script {
+    pub events: Vec<Event>
+}
+
+/// A line of a script that can't be parsed.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ScriptError {
+    /// The line number, starting from 1.
+    pub line: usize,
+    pub text: String
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid script line {}: {:?}", self.line, self.text)
+    }
+}
+
+impl core::error::Error for ScriptError {}
+
+impl Script {
+    /// Parse the text format, see the mod doc.
+    pub fn parse(text: &str) -> Result<Self, ScriptError> {
+        let mut events = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let content = line.split('#').next().unwrap_or("").trim();
+            if content.is_empty() {
+                continue;
+            }
+            let err = || ScriptError { line: i + 1, text: line.into() };
+            let mut parts = content.split_whitespace();
+            let direction = match parts.next() {
+                Some("R") => Direction::Read,
+                Some("W") => Direction::Write,
+                _ => return Err(err())
+            };
+            let mut num = || parts.next().and_then(parse_num).ok_or_else(err);
+            let (offset, width, value) = (num()? as usize, num()? as usize, num()?);
+            if parts.next().is_some() || !matches!(width, 1 | 2 | 4 | 8) {
+                return Err(err());
+            }
+            if width < 8 && value >> (width * 8) != 0 {
+                return Err(err());
+            }
+            events.push(Event { direction, offset, width, value });
+        }
+        Ok(Self { events })
+    }
+}
+
+fn parse_num(text: &str) -> Option<u64> {
+    match text.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok()
+    }
+}
+
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for event in &self.events {
+            writeln!(f, "{}", event)?;
+        }
+        Ok(())
+    }
+}
+
+/// ## Recording backend
+///
+/// Records every access through backend `B`.
+pub struct Recorder<B: RegisterBackend> {
+    backend: B,
+    script: RefCell<Script>
+}
+
+impl<B: RegisterBackend> Recorder<B> {
+    pub fn new(backend: B) -> Self {
+        Self {
+            backend,
+            script: RefCell::new(Script::default())
+        }
+    }
+
+    #[inline]
+    pub fn inner(&self) -> &B {
+        &self.backend
+    }
+
+    /// The accesses recorded so far.
+    pub fn script(&self) -> Script {
+        self.script.borrow().clone()
+    }
+
+    fn record(&self, direction: Direction, offset: usize, width: usize, value: u64) {
+        self.script.borrow_mut().events.push(Event { direction, offset, width, value });
+    }
+}
+
+impl<B: RegisterBackend> RegisterBackend for Recorder<B> {
+    fn read<T: UIntLike>(&self, offset: usize) -> T {
+        let val = self.backend.read::<T>(offset);
+        self.record(Direction::Read, offset, size_of::<T>(), val.to_u64());
+        val
+    }
+
+    fn write<T: UIntLike>(&self, offset: usize, val: T) {
+        self.record(Direction::Write, offset, size_of::<T>(), val.to_u64());
+        self.backend.write(offset, val);
+    }
+}
+
+/// ## Replaying backend
+///
+/// Replays a script against a driver using layout `L`, whose register names and bit
+/// fields decode the failure messages.
+pub struct Replayer<L: RegisterLayout> {
+    script: Script,
+    next: Cell<usize>,
+    _layout: PhantomData<fn() -> L>
+}
+
+impl<L: RegisterLayout> Replayer<L> {
+    pub fn new(script: Script) -> Self {
+        Self {
+            script,
+            next: Cell::new(0),
+            _layout: PhantomData
+        }
+    }
+
+    /// Panic if some accesses of the script didn't happen.
+    pub fn finish(self) {
+        self.check_done();
+    }
+
+    fn check_done(&self) {
+        let next = self.next.get();
+        if let Some(event) = self.script.events.get(next) {
+            panic!(
+                "register access {} missing: expected {} ({} accesses left)",
+                next, self.decode(event), self.script.events.len() - next
+            );
+        }
+    }
+
+    fn access(&self, got: Event) -> Event {
+        let next = self.next.get();
+        let Some(&expected) = self.script.events.get(next) else {
+            panic!("register access {} is extra: got {}", next, self.decode(&got));
+        };
+        let value_matters = got.direction == Direction::Write;
+        if expected.direction != got.direction
+            || expected.offset != got.offset
+            || expected.width != got.width
+            || (value_matters && expected.value != got.value)
+        {
+            let mut msg = format!(
+                "register access {} diverged:\n  expected {}\n  got      {}",
+                next, self.decode(&expected), self.decode(&got)
+            );
+            let (want, have) = (self.decode(&expected), self.decode(&got));
+            if expected.offset == got.offset && expected.width == got.width && value_matters {
+                for field in have.fields {
+                    let (want, have) = (field.display(want.value), field.display(have.value));
+                    if want != have {
+                        msg += &format!("\n  {}: {} != {}", field.name, want, have);
+                    }
+                }
+            }
+            panic!("{}", msg);
+        }
+        self.next.set(next + 1);
+        expected
+    }
+
+    fn decode(&self, event: &Event) -> Access {
+        Access::decode::<L>(event.direction, event.offset, event.width, event.value)
+    }
+}
+
+impl<L: RegisterLayout> Drop for Replayer<L> {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            self.check_done();
+        }
+    }
+}
+
+impl<L: RegisterLayout> RegisterBackend for Replayer<L> {
+    fn read<T: UIntLike>(&self, offset: usize) -> T {
+        let event = self.access(Event { direction: Direction::Read, offset, width: size_of::<T>(), value: 0 });
+        T::from_u64(event.value)
+    }
+
+    fn write<T: UIntLike>(&self, offset: usize, val: T) {
+        self.access(Event { direction: Direction::Write, offset, width: size_of::<T>(), value: val.to_u64() });
+    }
+}
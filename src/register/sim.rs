@@ -0,0 +1,151 @@
+//! ## crate::register::sim
+//!
+//! This mod contains `SimDevice`, a backend for simulating peripherals in driver
+//! tests. It keeps the registers of a layout in a `FakeDevice`, and runs behaviour
+//! hooks attached to registers on every access through the backend:
+//! - An on-read hook gets the stored value and returns the one the driver reads,
+//!   e.g. a status bit setting after N polls, or popping a FIFO.
+//! - An on-write hook gets the written value and decides what to store, e.g.
+//!   clearing write-1-to-clear flags, or pushing into a FIFO.
+//!
+//! Hooks get the `FakeDevice` too, to update other registers. A peripheral model is
+//! then a function attaching hooks to a `SimDevice`, reusable across tests:
+//!
+//! ```
+//! use re_reg::prelude::*;
+//! reg_bitfields! { SR(u32) [ RXNE [ 0 => 1 ] ] }
+//! registers_layout! {
+//!     UartRegs {
+//!         ( 0x00 => SR: RO<u32, SR::Reg> ),
+//!         ( 0x04 => DR: RW<u32> ),
+//!         @END
+//!     }
+//! }
+//!
+//! /// A loopback UART, bytes written to DR are read back from DR.
+//! fn loopback(sim: &mut SimDevice<'_, UartRegs>) {
+//!     sim.on_write(UartRegs::DR, |mem, val| {
+//!         mem.poke_reg(UartRegs::DR, val);
+//!         mem.poke_reg(UartRegs::SR, 1);
+//!     });
+//!     sim.on_read(UartRegs::DR, |mem, val| {
+//!         mem.poke_reg(UartRegs::SR, 0);
+//!         val
+//!     });
+//! }
+//!
+//! let mut sim = SimDevice::new();
+//! loopback(&mut sim);
+//! let dev = Device::<_, UartRegs>::new(sim);
+//! dev.reg(UartRegs::DR).write(0x42);
+//! assert!(dev.reg(UartRegs::SR).is_set(SR::RXNE));
+//! assert_eq!(dev.reg(UartRegs::DR).read(), 0x42);
+//! assert!(!dev.reg(UartRegs::SR).is_set(SR::RXNE));
+//! ```
+//!
+//! Only accesses through the backend (i.e. `Device`) run hooks, accesses through a
+//! layout borrowed from the `FakeDevice` don't.
+
+use std::boxed::Box;
+use std::cell::RefCell;
+use std::vec::Vec;
+
+use crate::int::UIntLike;
+
+use super::RegisterLayout;
+use super::InnerRegister;
+use super::backend::{RegDesc, RegisterBackend};
+use super::fake::FakeDevice;
+
+type ReadHook<'a, L> = Box<dyn FnMut(&FakeDevice<L>, u64) -> u64 + 'a>;
+type WriteHook<'a, L> = Box<dyn FnMut(&FakeDevice<L>, u64) + 'a>;
+
+/// ## Simulated device
+///
+/// Hooks may borrow state for `'a`, e.g. a FIFO the test inspects.
+pub struct SimDevice<'a, L: RegisterLayout> {
+    mem: FakeDevice<L>,
+    read_hooks: RefCell<Vec<(usize, ReadHook<'a, L>)>>,
+    write_hooks: RefCell<Vec<(usize, WriteHook<'a, L>)>>
+}
+
+impl<L: RegisterLayout> Default for SimDevice<'_, L> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, L: RegisterLayout> SimDevice<'a, L> {
+    /// Create a device with all registers 0 and no hooks.
+    pub fn new() -> Self {
+        Self {
+            mem: FakeDevice::new(),
+            read_hooks: RefCell::new(Vec::new()),
+            write_hooks: RefCell::new(Vec::new())
+        }
+    }
+
+    /// The registers' memory, accessing it runs no hook.
+    #[inline]
+    pub fn mem(&self) -> &FakeDevice<L> {
+        &self.mem
+    }
+
+    /// Run `f` on every read of the register described by `desc`. `f` gets the
+    /// stored value and returns the value read. It replaces the previous on-read
+    /// hook of the register.
+    pub fn on_read<I, F>(&mut self, desc: RegDesc<L, I>, mut f: F) -> &mut Self
+    where
+        I: InnerRegister,
+        F: FnMut(&FakeDevice<L>, I::Int) -> I::Int + 'a
+    {
+        let hook: ReadHook<'a, L> = Box::new(move |mem, val| f(mem, I::Int::from_u64(val)).to_u64());
+        Self::insert(self.read_hooks.get_mut(), desc.offset(), hook);
+        self
+    }
+
+    /// Run `f` on every write of the register described by `desc`, instead of
+    /// storing the value. It replaces the previous on-write hook of the register.
+    pub fn on_write<I, F>(&mut self, desc: RegDesc<L, I>, mut f: F) -> &mut Self
+    where
+        I: InnerRegister,
+        F: FnMut(&FakeDevice<L>, I::Int) + 'a
+    {
+        let hook: WriteHook<'a, L> = Box::new(move |mem, val| f(mem, I::Int::from_u64(val)));
+        Self::insert(self.write_hooks.get_mut(), desc.offset(), hook);
+        self
+    }
+
+    /// Make the register described by `desc` write-1-to-clear: writing a 1 bit
+    /// clears it, writing a 0 bit leaves it.
+    pub fn w1c<I: InnerRegister>(&mut self, desc: RegDesc<L, I>) -> &mut Self {
+        let offset = desc.offset();
+        self.on_write(desc, move |mem, val: I::Int| {
+            mem.poke(offset, mem.peek::<I::Int>(offset) & (!val));
+        })
+    }
+
+    fn insert<H>(hooks: &mut Vec<(usize, H)>, offset: usize, hook: H) {
+        hooks.retain(|(at, _)| *at != offset);
+        hooks.push((offset, hook));
+    }
+}
+
+impl<L: RegisterLayout> RegisterBackend for SimDevice<'_, L> {
+    fn read<T: UIntLike>(&self, offset: usize) -> T {
+        let val = self.mem.peek::<T>(offset);
+        let mut hooks = self.read_hooks.borrow_mut();
+        match hooks.iter_mut().find(|(at, _)| *at == offset) {
+            Some((_, hook)) => T::from_u64(hook(&self.mem, val.to_u64())),
+            None => val
+        }
+    }
+
+    fn write<T: UIntLike>(&self, offset: usize, val: T) {
+        let mut hooks = self.write_hooks.borrow_mut();
+        match hooks.iter_mut().find(|(at, _)| *at == offset) {
+            Some((_, hook)) => hook(&self.mem, val.to_u64()),
+            None => self.mem.poke(offset, val)
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! ## crate::register::split
+//!
+//! This mod contains registers wider than the bus, e.g. a 64-bit counter on a 32-bit
+//! bus. `SplitInnerRegister<T, H, O, R, E>` is a `T` register accessed as two `H`
+//! halves. With a little-endian `E` the low half is at the lower address, with a
+//! big-endian `E` the high half is, so the whole register has the byte order of a
+//! `Name(BE)` layout. The order `O` decides which half is accessed first:
+//! - `HighFirst`: writes go high then low. Reads go high, low, then high again, and
+//!   retry if the high half changed, so a counter carrying between the two halves
+//!   never reads torn. The retries are not bounded, a high half changing on every
+//!   read makes the read spin.
+//! - `LowFirst`: reads and writes go low then high, for hardware latching the high
+//!   half when the low half is accessed. Reads have no tear detection, hardware
+//!   without such a latch can read torn.
+//!
+//! The register implements `ReadableIO` and `WritableIO` with the full width `T`, so
+//! bit fields work as on any other register, and so does its `BackedRegister`, which
+//! accesses the halves through the backend. Use the `SPLIT<T, H, O, R>` kind in
+//! `registers_layout!{}`.
+
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::mem::size_of;
+
+use crate::int::UIntLike;
+
+use super::bitfield::{BitsLike, ReadableIO, WritableIO};
+use super::endian::{Endianness, NativeEndian};
+use super::fallible::InfallibleIO;
+use super::{InnerRegister, RegName};
+
+/// The access order of a split register's halves.
+pub trait SplitOrder {
+    const HIGH_FIRST: bool;
+}
+
+/// Access the high half first, reads are tear-free.
+pub struct HighFirst;
+
+/// Access the low half first, reads are only tear-free if the hardware latches the
+/// high half.
+pub struct LowFirst;
+
+impl SplitOrder for HighFirst {
+    const HIGH_FIRST: bool = true;
+}
+
+impl SplitOrder for LowFirst {
+    const HIGH_FIRST: bool = false;
+}
+
+/// ## Split register
+///
+/// `E` is the byte order of each half, and decides which half is at the lower
+/// address.
+#[repr(C)]
+pub struct SplitInnerRegister<T, H, O, R = (), E = NativeEndian>
+where
+    T: UIntLike,
+    H: UIntLike,
+    O: SplitOrder,
+    R: RegName + BitsLike<T>,
+    E: Endianness
+{
+    halves: [UnsafeCell<H>; 2],
+    _reg: PhantomData<(T, O, R, E)>
+}
+
+impl<T, H, O, R, E> SplitInnerRegister<T, H, O, R, E>
+where
+    T: UIntLike,
+    H: UIntLike,
+    O: SplitOrder,
+    R: RegName + BitsLike<T>,
+    E: Endianness
+{
+    #[inline]
+    fn read_half(&self, half: usize) -> H {
+        let val = E::from_hw(unsafe {
+            self.halves[half_slot::<E>(half)].get().read_volatile()
+        });
+        trace!(Read, self, at half * size_of::<H>(), val);
+        val
+    }
+
+    #[inline]
+    fn write_half(&self, half: usize, val: H) {
+        trace!(Write, self, at half * size_of::<H>(), val);
+        unsafe {
+            self.halves[half_slot::<E>(half)].get().write_volatile(E::to_hw(val));
+        }
+    }
+}
+
+#[inline]
+const fn half_bits<T, H>() -> usize {
+    const {
+        assert!(size_of::<T>() == 2 * size_of::<H>(), "A split register must be twice as wide as its halves");
+    }
+    size_of::<H>() * 8
+}
+
+/// The index of half `half` (0 for the low half, 1 for the high half) from the lower
+/// address, in byte order `E`.
+#[inline]
+pub(crate) const fn half_slot<E: Endianness>(half: usize) -> usize {
+    if E::BIG_ENDIAN { 1 - half } else { half }
+}
+
+/// Read a split register in order `O`, `read_half(0)` reads the low half and
+/// `read_half(1)` the high half. Use `half_slot()` to find a half's address.
+#[inline]
+pub(crate) fn read_split<T, H, O>(mut read_half: impl FnMut(usize) -> H) -> T
+where
+    T: UIntLike,
+    H: UIntLike,
+    O: SplitOrder
+{
+    let (lo, hi) = if O::HIGH_FIRST {
+        let mut hi = read_half(1);
+        // Unbounded, see the mod doc.
+        loop {
+            let lo = read_half(0);
+            let again = read_half(1);
+            if again == hi {
+                break (lo, hi);
+            }
+            hi = again;
+        }
+    } else {
+        let lo = read_half(0);
+        (lo, read_half(1))
+    };
+    T::from_u64(hi.to_u64() << half_bits::<T, H>() | lo.to_u64())
+}
+
+/// Write a split register in order `O`, see `read_split()`.
+#[inline]
+pub(crate) fn write_split<T, H, O>(val: T, mut write_half: impl FnMut(usize, H))
+where
+    T: UIntLike,
+    H: UIntLike,
+    O: SplitOrder
+{
+    let val = val.to_u64();
+    let (lo, hi) = (H::from_u64(val), H::from_u64(val >> half_bits::<T, H>()));
+    if O::HIGH_FIRST {
+        write_half(1, hi);
+        write_half(0, lo);
+    } else {
+        write_half(0, lo);
+        write_half(1, hi);
+    }
+}
+
+impl<T, H, O, R, E> InnerRegister for SplitInnerRegister<T, H, O, R, E>
+where
+    T: UIntLike,
+    H: UIntLike,
+    O: SplitOrder,
+    R: RegName + BitsLike<T>,
+    E: Endianness
+{
+    type Int = T;
+    type Name = R;
+    type Endian = E;
+}
+
+impl<T, H, O, R, E> InfallibleIO for SplitInnerRegister<T, H, O, R, E>
+where
+    T: UIntLike,
+    H: UIntLike,
+    O: SplitOrder,
+    R: RegName + BitsLike<T>,
+    E: Endianness
+{}
+
+impl<T, H, O, R, E> ReadableIO<T, R> for SplitInnerRegister<T, H, O, R, E>
+where
+    T: UIntLike,
+    H: UIntLike,
+    O: SplitOrder,
+    R: RegName + BitsLike<T>,
+    E: Endianness
+{
+    #[inline]
+    fn read(&self) -> T {
+        read_split::<T, H, O>(|i| self.read_half(i))
+    }
+}
+
+impl<T, H, O, R, E> WritableIO<T, R> for SplitInnerRegister<T, H, O, R, E>
+where
+    T: UIntLike,
+    H: UIntLike,
+    O: SplitOrder,
+    R: RegName + BitsLike<T>,
+    E: Endianness
+{
+    #[inline]
+    fn write(&self, val: T) {
+        write_split::<T, H, O>(val, |i, half| self.write_half(i, half));
+    }
+}
@@ -0,0 +1,215 @@
+//! ## crate::register::trace
+//!
+//! This mod contains register access tracing, for board bring-up. With the `trace`
+//! feature, every read and write of the built-in `InnerRegister` kinds calls the
+//! tracer installed with `set_tracer()`. Without the feature, the calls are compiled
+//! out entirely, and `set_tracer()` doesn't exist.
+//!
+//! A register only knows its address, so accesses are named after the layouts
+//! passed to `add_layout()`: the access carries the register's name and offset in
+//! its layout. Accesses outside those layouts carry no name and their address.
+//!
+//! Accesses of a part of a register, i.e. lanes, halves of split registers and
+//! SET/CLR/TOG aliases, carry the register's name and the part's offset, without
+//! fields. A lane or half at index `i` is `i` times its width into the register,
+//! counting from the least significant one.
+//!
+//! With `std`, `print_tracer()` prints every access to stderr, with the fields
+//! decoded when the register has bit fields from `reg_bitfields!{}`:
+//!
+//! ```text
+//! W CR @0x0 (32-bit) = 0x00000021 [EN=1, MODE=Fast]
+//! ```
+
+use core::fmt;
+#[cfg(feature = "trace")]
+use core::cell::UnsafeCell;
+#[cfg(feature = "trace")]
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+
+#[cfg(feature = "trace")]
+use crate::int::UIntLike;
+
+#[cfg(feature = "trace")]
+use super::RegInfo;
+use super::{FieldDesc, RegisterLayout};
+
+/// The direction of an access.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Direction {
+    Read,
+    Write
+}
+
+/// ## Register access
+#[derive(Clone, Copy, Debug)]
+pub struct Access {
+    /// The register's name in its layout, empty if it is unknown.
+    pub name: &'static str,
+    /// The register's offset in its layout, or its address if the layout is unknown.
+    pub offset: usize,
+    /// The register's width in bytes.
+    pub width: usize,
+    /// The value read or written, in logical bit order.
+    pub value: u64,
+    pub direction: Direction,
+    /// The register's bit fields.
+    pub fields: &'static [FieldDesc]
+}
+
+impl Access {
+    /// Decode a backend access of `width` bytes with value `raw` at `offset` of
+    /// layout `L`. The value is converted to logical bit order and decoded field by
+    /// field when the access covers a whole register, and kept as is otherwise.
+    pub fn decode<L: RegisterLayout>(direction: Direction, offset: usize, width: usize, raw: u64) -> Self {
+        let info = L::reg_info(offset);
+        let decoded = info.and_then(|info| Some((info.decode(width, raw)?, info.fields)));
+        let (value, fields) = decoded.unwrap_or((raw, &[]));
+        Access {
+            name: info.map_or("", |info| info.name),
+            offset,
+            width,
+            value,
+            direction,
+            fields
+        }
+    }
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dir = match self.direction {
+            Direction::Read => 'R',
+            Direction::Write => 'W'
+        };
+        let name = if self.name.is_empty() { "?" } else { self.name };
+        write!(
+            f, "{} {} @{:#x} ({}-bit) = {:#0w$x}",
+            dir, name, self.offset, self.width * 8, self.value, w = self.width * 2 + 2
+        )?;
+        for (i, field) in self.fields.iter().enumerate() {
+            let sep = if i == 0 { " [" } else { ", " };
+            write!(f, "{}{}={}", sep, field.name, field.display(self.value))?;
+        }
+        if !self.fields.is_empty() {
+            write!(f, "]")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "trace")]
+static TRACER: AtomicPtr<()> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Install the tracer called on every register access.
+#[cfg(feature = "trace")]
+pub fn set_tracer(tracer: fn(&Access)) {
+    TRACER.store(tracer as *mut (), Ordering::Release);
+}
+
+/// How many layouts `add_layout()` takes.
+#[cfg(feature = "trace")]
+pub const MAX_LAYOUTS: usize = 8;
+
+/// A layout passed to `add_layout()`.
+#[cfg(feature = "trace")]
+#[derive(Clone, Copy)]
+struct Layout {
+    base: usize,
+    size: usize,
+    reg_info: fn(usize) -> Option<RegInfo>
+}
+
+/// The layouts accesses are named after, each slot written once.
+#[cfg(feature = "trace")]
+struct Layouts {
+    claimed: AtomicUsize,
+    ready: [AtomicBool; MAX_LAYOUTS],
+    slots: [UnsafeCell<Option<Layout>>; MAX_LAYOUTS]
+}
+
+// SAFETY: a slot is only written by the single `add()` claiming it, and only read
+// once it is `ready`.
+#[cfg(feature = "trace")]
+unsafe impl Sync for Layouts {}
+
+#[cfg(feature = "trace")]
+impl Layouts {
+    fn add(&self, layout: Layout) -> bool {
+        let claim = self.claimed.fetch_update(Ordering::Acquire, Ordering::Relaxed, |n| {
+            (n < MAX_LAYOUTS).then_some(n + 1)
+        });
+        let Ok(i) = claim else {
+            return false;
+        };
+        // SAFETY: only this call claimed slot `i`, and nobody reads it before `ready`.
+        unsafe {
+            *self.slots[i].get() = Some(layout);
+        }
+        self.ready[i].store(true, Ordering::Release);
+        true
+    }
+
+    /// The layout containing `addr`, and the offset of `addr` in it.
+    fn find(&self, addr: usize) -> Option<(Layout, usize)> {
+        (0..MAX_LAYOUTS).filter(|&i| self.ready[i].load(Ordering::Acquire)).find_map(|i| {
+            // SAFETY: the slot is never written again once `ready`.
+            let layout = unsafe { (*self.slots[i].get())? };
+            let offset = addr.checked_sub(layout.base).filter(|&offset| offset < layout.size)?;
+            Some((layout, offset))
+        })
+    }
+}
+
+#[cfg(feature = "trace")]
+static LAYOUTS: Layouts = Layouts {
+    claimed: AtomicUsize::new(0),
+    ready: [const { AtomicBool::new(false) }; MAX_LAYOUTS],
+    slots: [const { UnsafeCell::new(None) }; MAX_LAYOUTS]
+};
+
+/// Name the accesses of `layout`'s registers after it. Takes up to `MAX_LAYOUTS`
+/// layouts, later calls give their layout back.
+#[cfg(feature = "trace")]
+pub fn add_layout<L: RegisterLayout>(layout: &'static L) -> Result<(), &'static L> {
+    let added = LAYOUTS.add(Layout {
+        base: layout as *const L as usize,
+        size: core::mem::size_of::<L>(),
+        reg_info: L::reg_info
+    });
+    if added { Ok(()) } else { Err(layout) }
+}
+
+/// Print the access to stderr.
+#[cfg(feature = "std")]
+pub fn print_tracer(access: &Access) {
+    std::eprintln!("{}", access);
+}
+
+/// Trace an access of `val` to the register at `addr`, `at` bytes into it for
+/// accesses of a part of it. Those don't cover the whole register, so they pass no
+/// `fields`.
+#[cfg(feature = "trace")]
+#[inline]
+pub(crate) fn trace<T: UIntLike>(
+    direction: Direction, addr: usize, at: usize, val: T, fields: &'static [FieldDesc]
+) {
+    let tracer = TRACER.load(Ordering::Acquire);
+    if tracer.is_null() {
+        return;
+    }
+    // SAFETY: the pointer was stored from a `fn(&Access)` in `set_tracer()`.
+    let tracer: fn(&Access) = unsafe { core::mem::transmute(tracer) };
+    let (name, offset) = match LAYOUTS.find(addr) {
+        Some((layout, offset)) => ((layout.reg_info)(offset).map_or("", |info| info.name), offset),
+        None => ("", addr)
+    };
+    tracer(&Access {
+        name,
+        offset: offset + at,
+        width: core::mem::size_of::<T>(),
+        value: val.to_u64(),
+        direction,
+        fields
+    });
+}
@@ -0,0 +1,190 @@
+//! ## crate::register::transaction
+//!
+//! This mod contains write coalescing transactions. A transaction accumulates
+//! `put()`, `set()` and `clear()` operations and applies them with a single read and
+//! a single write per touched register when it commits.
+//!
+//! Use `ReadWritableIO::transaction()` for a single register, or the `transaction()`
+//! method generated on every layout for several registers at once.
+
+use core::any::TypeId;
+use core::marker::PhantomData;
+
+use crate::int::UIntLike;
+
+use super::{InnerRegister, RegName, RegisterLayout};
+use super::backend::RegDesc;
+use super::bitfield::{Bits, MaskedVal, ReadableIO, WritableIO};
+use super::lock::rmw;
+
+/// ## Single register transaction
+pub struct Transaction<T: UIntLike, R: RegName = ()> {
+    pub(crate) mask: T,
+    pub(crate) val: T,
+    _reg: PhantomData<R>
+}
+
+impl<T: UIntLike, R: RegName> Transaction<T, R> {
+    pub(crate) fn new() -> Self {
+        Self {
+            mask: T::zero(),
+            val: T::zero(),
+            _reg: PhantomData
+        }
+    }
+
+    /// Put the value into target bits.
+    #[inline]
+    pub fn put(&mut self, val: MaskedVal<T, R>) -> &mut Self {
+        self.mask |= val.mask;
+        self.val = self.val & (!val.mask) | val.val;
+        self
+    }
+
+    /// Set target bits.
+    #[inline]
+    pub fn set(&mut self, bits: Bits<T, R>) -> &mut Self {
+        self.mask |= bits.mask;
+        self.val |= bits.mask;
+        self
+    }
+
+    /// Set target bits to 0.
+    #[inline]
+    pub fn clear(&mut self, bits: Bits<T, R>) -> &mut Self {
+        self.mask |= bits.mask;
+        self.val &= !bits.mask;
+        self
+    }
+
+    /// Whether any bit has been touched.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.mask == T::zero()
+    }
+
+    /// Apply the accumulated operations to `cur`.
+    #[inline]
+    pub(crate) fn apply(&self, cur: T) -> T {
+        cur & (!self.mask) | self.val
+    }
+}
+
+struct Entry<L> {
+    offset: usize,
+    /// Registers are keyed by offset and type.
+    reg: TypeId,
+    mask: u64,
+    val: u64,
+    commit: fn(&L, usize, u64, u64)
+}
+
+impl<L> Clone for Entry<L> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<L> Copy for Entry<L> {}
+
+/// ## Layout-wide transaction
+///
+/// Accumulates operations on the registers of layout `L`, which has `N` of them.
+/// Registers are committed in the order they were first touched.
+pub struct LayoutTransaction<'a, L: RegisterLayout, const N: usize> {
+    layout: &'a L,
+    entries: [Option<Entry<L>>; N]
+}
+
+/// Run a transaction on `layout`, this is what the `transaction()` method generated
+/// on layouts calls.
+#[inline]
+pub fn run_layout<'a, L, const N: usize, F>(layout: &'a L, f: F)
+where
+    L: RegisterLayout,
+    F: FnOnce(&mut LayoutTransaction<'a, L, N>)
+{
+    LayoutTransaction::run(layout, f);
+}
+
+impl<'a, L: RegisterLayout, const N: usize> LayoutTransaction<'a, L, N> {
+    #[inline]
+    pub(crate) fn run<F: FnOnce(&mut Self)>(layout: &'a L, f: F) {
+        let mut tx = Self {
+            layout,
+            entries: [None; N]
+        };
+        f(&mut tx);
+        tx.commit();
+    }
+
+    /// Put the value into target bits of the register described by `desc`.
+    #[inline]
+    pub fn put<I>(&mut self, desc: RegDesc<L, I>, val: MaskedVal<I::Int, I::Name>) -> &mut Self
+    where
+        I: InnerRegister + ReadableIO<I::Int, I::Name> + WritableIO<I::Int, I::Name> + 'static
+    {
+        self.update(desc, val.mask, val.val)
+    }
+
+    /// Set target bits of the register described by `desc`.
+    #[inline]
+    pub fn set<I>(&mut self, desc: RegDesc<L, I>, bits: Bits<I::Int, I::Name>) -> &mut Self
+    where
+        I: InnerRegister + ReadableIO<I::Int, I::Name> + WritableIO<I::Int, I::Name> + 'static
+    {
+        self.update(desc, bits.mask, bits.mask)
+    }
+
+    /// Set target bits of the register described by `desc` to 0.
+    #[inline]
+    pub fn clear<I>(&mut self, desc: RegDesc<L, I>, bits: Bits<I::Int, I::Name>) -> &mut Self
+    where
+        I: InnerRegister + ReadableIO<I::Int, I::Name> + WritableIO<I::Int, I::Name> + 'static
+    {
+        self.update(desc, bits.mask, I::Int::zero())
+    }
+
+    fn update<I>(&mut self, desc: RegDesc<L, I>, mask: I::Int, val: I::Int) -> &mut Self
+    where
+        I: InnerRegister + ReadableIO<I::Int, I::Name> + WritableIO<I::Int, I::Name> + 'static
+    {
+        let (offset, reg) = (desc.offset(), TypeId::of::<I>());
+        let (mask, val) = (mask.to_u64(), val.to_u64());
+        if let Some(entry) = self.entries.iter_mut().flatten().find(|entry| entry.offset == offset && entry.reg == reg) {
+            entry.mask |= mask;
+            entry.val = entry.val & !mask | val;
+            return self;
+        }
+        // `N` is the number of registers in `L`, and each of them has a slot.
+        let Some(slot) = self.entries.iter_mut().find(|entry| entry.is_none()) else {
+            unreachable!("a layout register is missing a transaction slot")
+        };
+        *slot = Some(Entry {
+            offset,
+            reg,
+            mask,
+            val,
+            commit: commit::<L, I>
+        });
+        self
+    }
+
+    fn commit(self) {
+        for entry in self.entries.iter().flatten() {
+            (entry.commit)(self.layout, entry.offset, entry.mask, entry.val);
+        }
+    }
+}
+
+fn commit<L, I>(layout: &L, offset: usize, mask: u64, val: u64)
+where
+    L: RegisterLayout,
+    I: InnerRegister + ReadableIO<I::Int, I::Name> + WritableIO<I::Int, I::Name> + 'static
+{
+    let Some(reg) = layout.reg_ref::<I>(offset) else {
+        panic!("no such register at offset {:#x} of the layout", offset)
+    };
+    let (mask, val) = (I::Int::from_u64(mask), I::Int::from_u64(val));
+    rmw(|| reg.write(reg.read() & (!mask) | val));
+}
@@ -0,0 +1,119 @@
+use std::cell::Cell;
+
+use re_reg::prelude::*;
+
+#[test]
+fn test_put_back_accesses() {
+    let reg = CountingReg::<u32, F1::Reg>::new(0xf0);
+
+    reg.put_back(F1::B1.val(0b10) + F1::B2.val(0b01));
+    assert_eq!(reg.value(), 0xf6);
+    assert_eq!(reg.counts(), (1, 1));
+
+    reg.set_back(F1::B1);
+    assert_eq!(reg.counts(), (2, 2));
+    reg.clear(F1::B1 + F1::B2);
+    assert_eq!(reg.counts(), (3, 3));
+    assert_eq!(reg.value(), 0xf0);
+}
+
+#[test]
+fn test_write_accesses() {
+    let reg = CountingReg::<u32, F1::Reg>::new(0xf0);
+
+    reg.put(F1::B2.val(0b11));
+    reg.set(F1::B1);
+    reg.clear_all();
+    assert_eq!(reg.counts(), (0, 3));
+}
+
+#[test]
+fn test_poll_rereads() {
+    let dev = Device::<_, Regs>::new(ReadyAfter::new(5));
+    while !dev.reg(Regs::SR).is_set(F1::RDY) {}
+    assert_eq!(dev.backend().reads.get(), 5);
+
+    let dev = Device::<_, Regs>::new(ReadyAfter::new(5));
+    while !dev.reg(Regs::CR).is_set(F1::RDY) {}
+    assert_eq!(dev.backend().reads.get(), 5);
+}
+
+/// Test-only device backend which counts reads, and sets RDY in every register once
+/// it has been read `n` times.
+struct ReadyAfter {
+    n: usize,
+    reads: Cell<usize>
+}
+
+impl ReadyAfter {
+    fn new(n: usize) -> Self {
+        Self { n, reads: Cell::new(0) }
+    }
+}
+
+impl RegisterBackend for ReadyAfter {
+    fn read<T: UIntLike>(&self, _offset: usize) -> T {
+        let reads = self.reads.get() + 1;
+        self.reads.set(reads);
+        T::from_u64(if reads >= self.n { 1 << 4 } else { 0 })
+    }
+
+    fn write<T: UIntLike>(&self, _offset: usize, _val: T) {}
+}
+
+/// Test-only register backend which counts every access.
+struct CountingReg<T: UIntLike, R: RegName> {
+    val: Cell<T>,
+    reads: Cell<usize>,
+    writes: Cell<usize>,
+    _reg: PhantomData<R>
+}
+
+impl<T: UIntLike, R: RegName> CountingReg<T, R> {
+    fn new(val: T) -> Self {
+        Self {
+            val: Cell::new(val),
+            reads: Cell::new(0),
+            writes: Cell::new(0),
+            _reg: PhantomData
+        }
+    }
+
+    fn value(&self) -> T {
+        self.val.get()
+    }
+
+    fn counts(&self) -> (usize, usize) {
+        (self.reads.get(), self.writes.get())
+    }
+}
+
+impl<T: UIntLike, R: RegName> ReadableIO<T, R> for CountingReg<T, R> {
+    fn read(&self) -> T {
+        self.reads.set(self.reads.get() + 1);
+        self.val.get()
+    }
+}
+
+impl<T: UIntLike, R: RegName> WritableIO<T, R> for CountingReg<T, R> {
+    fn write(&self, val: T) {
+        self.writes.set(self.writes.get() + 1);
+        self.val.set(val);
+    }
+}
+
+reg_bitfields! {
+    F1(u32) [
+        B1  [ 0 => 2 ],
+        B2  [ 2 => 2 ],
+        RDY [ 4 => 1 ]
+    ]
+}
+
+registers_layout! {
+    Regs {
+        ( 0x00 => CR: RW<u32, F1::Reg> ),
+        ( 0x04 => SR: RO<u32, F1::Reg> ),
+        @END
+    }
+}
@@ -0,0 +1,44 @@
+use re_reg::prelude::*;
+
+reg_bitfields! {
+    CTRL(u32) [
+        EN [ 0 => 1 ],
+        MODE [ 4 => 2 ]
+    ]
+}
+
+registers_layout! {
+    GpioRegs {
+        ( 0x00 => CTRL: SCT<u32, CTRL::Reg> ),
+        ( 0x10 => DR: RW<u32> ),
+        @END
+    }
+}
+
+#[test]
+fn test_alias_writes() {
+    let io = FakeDevice::<GpioRegs>::new();
+    io.poke_reg(GpioRegs::CTRL, 0x31);
+
+    assert_eq!(GpioRegs::DR.offset(), 0x10);
+    assert!(io.CTRL.is_set(CTRL::EN));
+    io.CTRL.set_back(CTRL::EN);
+    io.CTRL.clear(CTRL::MODE);
+    io.CTRL.toggle(CTRL::EN + CTRL::MODE);
+    // The register itself is never written.
+    assert_eq!(words(&io), [0x31, 0x01, 0x30, 0x31, 0]);
+}
+
+#[test]
+fn test_alias_put_back() {
+    let io = FakeDevice::<GpioRegs>::new();
+
+    io.CTRL.put_back(CTRL::MODE.val(0b01));
+    assert_eq!(words(&io), [0, 0x10, 0x20, 0, 0]);
+    io.CTRL.write(0x11);
+    assert_eq!(io.CTRL.get(CTRL::MODE), 0b01);
+}
+
+fn words(io: &FakeDevice<GpioRegs>) -> [u32; 5] {
+    core::array::from_fn(|i| io.peek(i * 4))
+}
@@ -0,0 +1,137 @@
+use std::future::Future;
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+
+use re_reg::prelude::*;
+use re_reg::Error;
+
+#[test]
+fn test_async_read_write() {
+    let mut dev = AsyncBusDevice::<_, ExpanderRegs>::new(FakeBus::new());
+
+    block_on(async {
+        dev.reg(ExpanderRegs::DIR).set_back(DIR::P0 + DIR::P3).await.unwrap();
+        dev.reg(ExpanderRegs::DIR).clear(DIR::P0).await.unwrap();
+        assert_eq!(dev.reg(ExpanderRegs::DIR).read().await, Ok(0b1000));
+        assert_eq!(dev.reg(ExpanderRegs::INPUT).get(IN::HI).await, Ok(0xa5));
+        assert_eq!(dev.reg(ExpanderRegs::INPUT).is_set(IN::LO).await, Ok(false));
+
+        dev.reg(ExpanderRegs::INPUT_LATCH).take().await.unwrap();
+    });
+    assert_eq!(dev.bus_mut().transfers, 8);
+}
+
+#[test]
+fn test_async_modify() {
+    let mut dev = AsyncBusDevice::<_, ExpanderRegs>::new(FakeBus::new());
+
+    block_on(async {
+        dev.reg(ExpanderRegs::DIR).write(0b0110).await.unwrap();
+        // Mirror P1 into P3.
+        dev.reg(ExpanderRegs::DIR).modify(|cur| DIR::P3.val(cur.get(DIR::P1))).await.unwrap();
+        assert_eq!(dev.reg(ExpanderRegs::DIR).read().await, Ok(0b1110));
+    });
+}
+
+#[test]
+fn test_async_error() {
+    let mut dev = AsyncBusDevice::<_, ExpanderRegs>::new(FakeBus::new());
+    dev.bus_mut().nack = true;
+
+    block_on(async {
+        assert_eq!(dev.reg(ExpanderRegs::DIR).set_back(DIR::P0).await, Err(Error::Bus(Nack)));
+    });
+    assert_eq!(dev.bus_mut().transfers, 0);
+}
+
+fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = pin!(fut);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(out) = fut.as_mut().poll(&mut cx) {
+            return out;
+        }
+    }
+}
+
+/// Completes after being polled twice, like a transfer waiting for an interrupt.
+struct Yield(bool);
+
+impl Future for Yield {
+    type Output = ();
+
+    fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Nack;
+
+struct FakeBus {
+    mem: [u8; 16],
+    transfers: usize,
+    nack: bool
+}
+
+impl FakeBus {
+    fn new() -> Self {
+        let mut mem = [0; 16];
+        mem[0] = 0xa5;
+        mem[1] = 0x5a;
+        Self { mem, transfers: 0, nack: false }
+    }
+}
+
+impl AsyncRegisterBus for FakeBus {
+    type Error = Nack;
+    type Addr = u8;
+
+    async fn read_regs(&mut self, addr: u8, buf: &mut [u8]) -> Result<(), Nack> {
+        Yield(false).await;
+        if self.nack {
+            return Err(Nack);
+        }
+        self.transfers += 1;
+        let addr = addr as usize;
+        buf.copy_from_slice(&self.mem[addr..addr + buf.len()]);
+        Ok(())
+    }
+
+    async fn write_regs(&mut self, addr: u8, data: &[u8]) -> Result<(), Nack> {
+        Yield(false).await;
+        if self.nack {
+            return Err(Nack);
+        }
+        self.transfers += 1;
+        let addr = addr as usize;
+        self.mem[addr..addr + data.len()].copy_from_slice(data);
+        Ok(())
+    }
+}
+
+reg_bitfields! {
+    DIR(u8) [
+        P0 [ 0 => 1 ],
+        P1 [ 1 => 1 ],
+        P3 [ 3 => 1 ]
+    ],
+    IN(u16) [
+        LO [ 0 => 8 ],
+        HI [ 8 => 8 ]
+    ]
+}
+
+bus_registers! {
+    ExpanderRegs {
+        ( 0x00 => INPUT: RO<u16, IN::Reg> ),
+        ( 0x02 => INPUT_LATCH: RC<u16, IN::Reg> ),
+        ( 0x06 => DIR: RW<u8, DIR::Reg> ),
+    }
+}
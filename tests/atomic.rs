@@ -0,0 +1,54 @@
+use re_reg::prelude::*;
+
+reg_bitfields! {
+    MBOX(u32) [
+        FULL [ 0 => 1 ],
+        ACK [ 1 => 1 ],
+        CHAN [ 8 => 8 ]
+    ]
+}
+
+registers_layout! {
+    Shared {
+        ( 0x00 => MBOX: ATOMIC<u32, MBOX::Reg> ),
+        ( 0x04 => FLAGS: ATOMIC<u32> ),
+        @END
+    }
+}
+
+#[test]
+fn test_atomic_ops() {
+    let io = FakeDevice::<Shared>::new();
+
+    assert_eq!(io.MBOX.set_back(MBOX::FULL), 0);
+    assert_eq!(io.MBOX.put_back(MBOX::CHAN.val(0x12)), 0x1);
+    assert_eq!(io.MBOX.toggle(MBOX::ACK + MBOX::FULL), 0x1201);
+    assert_eq!(io.MBOX.read(), 0x1202);
+    io.MBOX.clear(MBOX::ACK);
+    assert_eq!(io.MBOX.get(MBOX::CHAN), 0x12);
+    assert!(!io.MBOX.is_set(MBOX::ACK));
+    io.MBOX.put(MBOX::FULL.val(1));
+    assert_eq!(io.peek_reg(Shared::MBOX), 0x1);
+}
+
+#[test]
+fn test_atomic_concurrent() {
+    let fake = FakeDevice::<Shared>::new();
+    let io = fake.regs();
+
+    std::thread::scope(|s| {
+        for bit in 0..16 {
+            s.spawn(move || {
+                let bits = Bits::new(bit, 1 << bit);
+                for _ in 0..1000 {
+                    io.FLAGS.set_back(bits);
+                    io.FLAGS.toggle(bits);
+                    io.FLAGS.put_back(bits.val(1));
+                    io.FLAGS.clear(bits);
+                }
+                io.FLAGS.set_back(bits);
+            });
+        }
+    });
+    assert_eq!(fake.peek_reg(Shared::FLAGS), 0xffff);
+}
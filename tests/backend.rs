@@ -0,0 +1,88 @@
+use std::cell::Cell;
+
+use re_reg::prelude::*;
+
+#[test]
+fn test_fake_backend() {
+    let dev = Device::<_, RB>::new(Fake::default());
+
+    dev.reg(RB::CR).put_back(C1::EN + C1::MODE.val(C1::Fast));
+    assert_eq!(dev.backend().peek(0x00), 0b0111);
+    dev.reg(RB::CR).clear(C1::EN);
+    assert_eq!(dev.reg(RB::CR).get(C1::MODE), C1::Fast);
+    assert!(!dev.reg(RB::CR).is_set(C1::EN));
+
+    dev.backend().poke(0x04, 0x1ff);
+    assert_eq!(dev.reg(RB::SR).read(), 0x1ff);
+    dev.reg(RB::DR).write(0xab);
+    assert_eq!(dev.backend().peek(0x06), 0xab);
+    dev.backend().poke(0x08, 0b10);
+    assert!(dev.reg(RB::ISR).take().is_set(C2::TXE));
+
+    let ws = dev.reg(RB::WS).shadowed(0);
+    ws.put_back(C1::MODE.val(C1::Slow));
+    ws.set_back(C1::EN);
+    assert_eq!((ws.shadow(), dev.backend().peek(0x0c)), (0b0110, 0b0110));
+}
+
+#[test]
+fn test_mmio_backend() {
+    let fake = FakeDevice::<RB>::new();
+    fake.poke_reg(RB::SR, 0x55);
+    let dev = Device::<_, RB>::new(unsafe { Mmio::new(fake.regs() as *const _ as usize) });
+
+    assert_eq!(RB::SR.offset(), 0x04);
+    assert_eq!(dev.reg(RB::SR).read(), 0x55);
+    dev.reg(RB::CR).put_back(C1::MODE.val(C1::Slow));
+    assert_eq!(fake.peek_reg(RB::CR), 0b0010);
+}
+
+/// In-memory backend, every offset holds a value of any width.
+#[derive(Default)]
+struct Fake {
+    mem: [Cell<u64>; 16]
+}
+
+impl Fake {
+    fn peek(&self, offset: usize) -> u64 {
+        self.mem[offset].get()
+    }
+
+    fn poke(&self, offset: usize, val: u64) {
+        self.mem[offset].set(val);
+    }
+}
+
+impl RegisterBackend for Fake {
+    fn read<T: UIntLike>(&self, offset: usize) -> T {
+        T::from_u64(self.mem[offset].get())
+    }
+
+    fn write<T: UIntLike>(&self, offset: usize, val: T) {
+        self.mem[offset].set(val.to_u64());
+    }
+}
+
+reg_bitfields! {
+    C1(u32) [
+        EN   [ 2 => 1 ],
+        MODE [ 0 => 2 ] {
+            Slow = 0b10,
+            Fast = 0b11
+        }
+    ],
+    C2(u32) [
+        TXE  [ 1 => 1 ]
+    ]
+}
+
+registers_layout! {
+    RB {
+        ( 0x00 => CR: RW<u32, C1::Reg> ),
+        ( 0x04 => SR: RO<u16> ),
+        ( 0x06 => DR: WO<u16> ),
+        ( 0x08 => ISR: RC<u32, C2::Reg> ),
+        ( 0x0c => WS: WOS<u32, C1::Reg> ),
+        @END
+    }
+}
@@ -0,0 +1,67 @@
+use re_reg::prelude::*;
+
+reg_bitfields! {
+    CTRL(u32) [
+        EN [ 0 => 1 ],
+        BANK [ 4 => 2 ]
+    ]
+}
+
+registers_layout! {
+    DevRegs {
+        ( 0x00 => CTRL: RW<u32, CTRL::Reg> ),
+        ( 0x04 => _banked ),
+        ( 0x0c => STATUS: RO<u32> ),
+        @END
+        @BANKS CTRL[CTRL::BANK] {
+            Bank0 = 0 => Bank0Regs,
+            Bank2 = 2 => Bank2Regs
+        }
+    }
+}
+
+registers_layout! {
+    Bank0Regs {
+        ( 0x00 => _ctrl ),
+        ( 0x04 => GAIN: RW<u32> ),
+        @END
+    }
+}
+
+registers_layout! {
+    Bank2Regs {
+        ( 0x00 => _ctrl ),
+        ( 0x04 => CAL_LO: RW<u32> ),
+        ( 0x08 => CAL_HI: RW<u32> ),
+        @END
+    }
+}
+
+#[test]
+fn test_bank_select_and_restore() {
+    let mut io = FakeDevice::<DevRegs>::new();
+    io.poke_reg(DevRegs::CTRL, 0x0000_0001);
+
+    {
+        let bank = io.bank::<Bank2>();
+        assert_eq!(bank.owner().CTRL.get(CTRL::BANK), 2);
+        // The selector write keeps the other bits.
+        assert!(bank.owner().CTRL.is_set(CTRL::EN));
+        bank.CAL_HI.write(0xdead_beef);
+    }
+    assert_eq!(io.peek::<u32>(0x08), 0xdead_beef);
+    assert_eq!(io.CTRL.get(CTRL::BANK), 0);
+    assert_eq!(io.peek_reg(DevRegs::CTRL), 0x0000_0001);
+}
+
+#[test]
+fn test_bank_switch() {
+    let mut io = FakeDevice::<DevRegs>::new();
+    io.poke_reg(DevRegs::CTRL, 0x0000_0020);
+
+    io.bank::<Bank0>().GAIN.write(7);
+    assert_eq!(io.CTRL.get(CTRL::BANK), 2);
+    assert_eq!(io.bank::<Bank2>().CAL_LO.read(), 7);
+    assert_eq!(io.bank::<Bank0>().GAIN.read(), 7);
+    assert_eq!(io.CTRL.get(CTRL::BANK), 2);
+}
@@ -0,0 +1,86 @@
+use std::cell::{Cell, RefCell};
+
+use re_reg::prelude::*;
+
+thread_local! {
+    static LOG: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+fn log(entry: String) {
+    LOG.with(|log| log.borrow_mut().push(entry));
+}
+
+fn take_log() -> Vec<String> {
+    LOG.with(|log| log.take())
+}
+
+/// Logs the barriers instead of issuing them.
+struct LogBarrier;
+
+impl Barrier for LogBarrier {
+    fn fence() {
+        log("fence".into());
+    }
+
+    fn release() {
+        log("release".into());
+    }
+
+    fn acquire() {
+        log("acquire".into());
+    }
+}
+
+#[derive(Default)]
+struct Fake {
+    mem: [Cell<u32>; 4]
+}
+
+impl RegisterBackend for Fake {
+    fn read<T: UIntLike>(&self, offset: usize) -> T {
+        log(format!("read {:x}", offset));
+        T::from_u64(self.mem[offset / 4].get() as u64)
+    }
+
+    fn write<T: UIntLike>(&self, offset: usize, val: T) {
+        log(format!("write {:x}", offset));
+        self.mem[offset / 4].set(val.to_u64() as u32);
+    }
+}
+
+reg_bitfields! {
+    CR(u32) [
+        START [ 0 => 1 ]
+    ]
+}
+
+registers_layout! {
+    DmaRegs {
+        ( 0x00 => CR: RW<u32, CR::Reg> ),
+        ( 0x04 => SR: RO<u32> ),
+        @END
+    }
+}
+
+#[test]
+fn test_fenced_device() {
+    let dev = Device::<_, DmaRegs>::new(Fenced::<_, LogBarrier>::new(Fake::default()));
+
+    dev.reg(DmaRegs::CR).set_back(CR::START);
+    assert_eq!(dev.reg(DmaRegs::SR).read(), 0);
+    assert_eq!(take_log(), ["read 0", "acquire", "release", "write 0", "read 4", "acquire"]);
+    assert_eq!(dev.backend().inner().mem[0].get(), 1);
+}
+
+#[test]
+fn test_release_acquire() {
+    let dev = Device::<_, DmaRegs>::new(Fake::default());
+    let buf = [1u8, 2, 3];
+
+    dev.reg(DmaRegs::CR).write_release::<LogBarrier>(buf.len() as u32);
+    assert_eq!(dev.reg(DmaRegs::CR).read_acquire::<LogBarrier>(), 3);
+    assert_eq!(take_log(), ["release", "write 0", "read 0", "acquire"]);
+    // The default fences still work.
+    dev.reg(DmaRegs::CR).write_release::<AtomicBarrier>(0);
+    assert_eq!(dev.reg(DmaRegs::CR).read_acquire::<AtomicBarrier>(), 0);
+}
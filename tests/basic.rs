@@ -3,16 +3,17 @@ use re_reg::prelude::*;
 
 #[test]
 fn test_read() {
-    let a = A { v1: 0, v2: 0b11100000 };
-    let io = IO::<RA>::new(&a as *const _ as usize);
+    let io = FakeDevice::<RA>::new();
+    io.poke(0x04, 0b11100000u16);
 
     assert_eq!(io.VY.read(), 0b11100000);
 }
 
 #[test]
 fn test_get_bits() {
-    let a = A { v1: 0b11000011, v2: 0b10100000 };
-    let io = IO::<RA>::new(&a as *const _ as usize);
+    let io = FakeDevice::<RA>::new();
+    io.poke(0x00, 0b11000011u32);
+    io.poke(0x04, 0b10100000u16);
 
     assert_eq!(io.VY.get(F2::B1), 1);
     assert_eq!(io.VY.get(F2::B2), 0);
@@ -23,17 +24,17 @@ fn test_get_bits() {
 
 #[test]
 fn test_write() {
-    let a = A { v1: 0, v2: 0 };
-    let io = IO::<RA>::new(&a as *const _ as usize);
+    let io = FakeDevice::<RA>::new();
 
     io.VX.write(0b11010010);
     assert_eq!(io.VX.read(), 0b11010010);
+    assert_eq!(io.peek::<u32>(0x00), 0b11010010);
+    assert_eq!(io.peek_reg(RA::VY), 0);
 }
 
 #[test]
 fn test_write_combination() {
-    let a = A { v1: 0, v2: 0 };
-    let io = IO::<RA>::new(&a as *const _ as usize);
+    let io = FakeDevice::<RA>::new();
 
     io.VX.put_back(
         F1::B1.val(F1::B1State1)
@@ -45,8 +46,7 @@ fn test_write_combination() {
 
 #[test]
 fn test_set_bits() {
-    let a = A { v1: 0, v2: 0 };
-    let io = IO::<RA>::new(&a as *const _ as usize);
+    let io = FakeDevice::<RA>::new();
 
     io.VX.put_back(
         F1::B2
@@ -60,8 +60,8 @@ fn test_set_bits() {
 
 #[test]
 fn test_clear() {
-    let a = A { v1: 0xffffffff, v2: 0 };
-    let io = IO::<RA>::new(&a as *const _ as usize);
+    let io = FakeDevice::<RA>::new();
+    io.poke(0x00, 0xffffffffu32);
 
     io.VX.clear(F1::B2 + F1::B3 + F1::B1);
         // .clear(F1::B1);
@@ -70,36 +70,6 @@ fn test_clear() {
     assert_eq!(io.VX.read(), 0);
 }
 
-#[allow(dead_code)]
-struct A {
-    pub v1: u32,
-    pub v2: u16
-}
-
-struct IO<T> {
-    base: usize,
-    _daio: PhantomData<*const T>
-}
-
-impl<T> IO<T> {
-    pub const fn new(base: usize) -> Self {
-        Self {
-            base,
-            _daio: PhantomData
-        }
-    }
-}
-
-impl<T> core::ops::Deref for IO<T> {
-    type Target = T;
-
-    fn deref(&self) -> &Self::Target {
-        unsafe {
-            &*(self.base as *const _)
-        }
-    }
-}
-
 reg_bitfields! {
     F1(u32) [
         B1 [ 0 => 2 ] {
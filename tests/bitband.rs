@@ -0,0 +1,79 @@
+use std::cell::Cell;
+
+use re_reg::prelude::*;
+
+reg_bitfields! {
+    ODR(u32) [
+        PIN0 [ 0 => 1 ],
+        PIN13 [ 13 => 1 ]
+    ]
+}
+
+registers_layout! {
+    GpioRegs {
+        ( 0x00 => IDR: RO<u32> ),
+        ( 0x04 => ODR: RW<u32, ODR::Reg> ),
+        ( 0x08 => BE: RW_BE<u32, ODR::Reg> ),
+        @END
+    }
+}
+
+#[test]
+fn test_bitband_address() {
+    // SAFETY: only addresses are computed, nothing is accessed.
+    let (peripheral, sram) = unsafe { (BitBand::peripheral(), BitBand::sram()) };
+    assert_eq!(peripheral.alias_addr(0x4002_0014, 3), 0x4240_028c);
+    assert_eq!(sram.alias_addr(0x2000_0000, 0), 0x2200_0000);
+    assert!(!sram.contains(0x2010_0000));
+
+    let model = Model::new();
+    let io = &model.mem;
+    let band = model.band();
+    assert_eq!(band.alias_of::<_, ODR::PIN13>(&io.ODR), model.alias_addr(4 + 1, 5));
+    assert_eq!(band.alias_of::<_, ODR::PIN0>(&io.BE), model.alias_addr(8 + 3, 0));
+}
+
+#[test]
+fn test_bitband_model() {
+    let model = Model::new();
+    let io = &model.mem;
+    let band = model.band();
+
+    band.set_bit_atomic::<_, ODR::PIN13>(&io.ODR);
+    band.set_bit_atomic::<_, ODR::PIN0>(&io.ODR);
+    band.clear_bit_atomic::<_, ODR::PIN0>(&io.ODR);
+    model.sync();
+    assert_eq!(io.ODR.read(), 1 << 13);
+    assert!(band.read_bit::<_, ODR::PIN13>(&io.ODR));
+}
+
+/// The layout's 12 bytes of memory and their alias words. `sync()` plays the hardware, moving
+/// the alias words into the memory bits.
+struct Model {
+    mem: FakeDevice<GpioRegs>,
+    alias: [Cell<u32>; 96]
+}
+
+impl Model {
+    fn new() -> Self {
+        Self {
+            mem: FakeDevice::new(),
+            alias: core::array::from_fn(|_| Cell::new(0))
+        }
+    }
+
+    fn band(&self) -> BitBand {
+        unsafe { BitBand::new(self.mem.regs() as *const _ as usize, self.alias.as_ptr() as usize, 12) }
+    }
+
+    fn alias_addr(&self, byte: usize, bit: usize) -> usize {
+        &self.alias[byte * 8 + bit] as *const _ as usize
+    }
+
+    fn sync(&self) {
+        for byte in 0..12 {
+            let val = (0..8).fold(0, |val, bit| val | (self.alias[byte * 8 + bit].get() as u8 & 1) << bit);
+            self.mem.poke::<u8>(byte, val);
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use re_reg::prelude::*;
+use re_reg::Error;
+
+#[test]
+fn test_bus_read_write() {
+    let dev = BusDevice::<_, SensorRegs>::new(FakeBus::<true>::new());
+
+    assert_eq!(dev.reg(SensorRegs::ID).try_read(), Ok(0x60));
+    dev.reg(SensorRegs::CTRL).try_put_back(CTRL::MODE.val(CTRL::Normal) + CTRL::OSRS.val(0b101)).unwrap();
+    assert_eq!(dev.reg(SensorRegs::CTRL).try_get(CTRL::OSRS), Ok(0b101));
+    dev.reg(SensorRegs::CTRL).try_clear(CTRL::MODE).unwrap();
+    assert_eq!(dev.reg(SensorRegs::CTRL).try_read(), Ok(0b10100));
+
+    let bus = dev.release();
+    // A single transfer for each access: ID, CTRL read + write, CTRL get, CTRL read + write, CTRL read.
+    assert_eq!(bus.transfers, 7);
+}
+
+#[test]
+fn test_bus_wide_registers() {
+    let mut dev = BusDevice::<_, SensorRegs>::new(FakeBus::<true>::new());
+
+    dev.bus_mut().mem[0xfa] = 0x12;
+    dev.bus_mut().mem[0xfb] = 0x34;
+    assert_eq!(dev.reg(SensorRegs::TEMP).try_read(), Ok(0x1234));
+
+    dev.reg(SensorRegs::THRES).try_write(0xabcd).unwrap();
+    assert_eq!(&dev.bus_mut().mem[0x10..0x12], &[0xab, 0xcd]);
+}
+
+#[test]
+fn test_bus_no_auto_increment() {
+    let mut dev = BusDevice::<_, SensorRegs>::new(FakeBus::<false>::new());
+
+    dev.bus_mut().mem[0xfa] = 0x56;
+    dev.bus_mut().mem[0xfb] = 0x78;
+    assert_eq!(dev.reg(SensorRegs::TEMP).try_read(), Ok(0x5678));
+    dev.reg(SensorRegs::THRES).try_write(0xabcd).unwrap();
+    assert_eq!(&dev.bus_mut().mem[0x10..0x12], &[0xab, 0xcd]);
+    assert_eq!(dev.bus_mut().transfers, 4);
+}
+
+#[test]
+fn test_bus_error() {
+    let mut dev = BusDevice::<_, SensorRegs>::new(FakeBus::<true>::new());
+
+    dev.bus_mut().nack = true;
+    assert_eq!(dev.reg(SensorRegs::ID).try_read(), Err(Error::Bus(Nack)));
+    assert_eq!(dev.reg(SensorRegs::CTRL).try_set_back(CTRL::MODE), Err(Error::Bus(Nack)));
+    assert_eq!(dev.reg(SensorRegs::STATUS).take().map(|s| s.read()), Err(Error::Bus(Nack)));
+}
+
+#[derive(Debug, PartialEq)]
+struct Nack;
+
+struct FakeBus<const AI: bool> {
+    mem: [u8; 256],
+    transfers: usize,
+    nack: bool
+}
+
+impl<const AI: bool> FakeBus<AI> {
+    fn new() -> Self {
+        let mut mem = [0; 256];
+        mem[0xd0] = 0x60;
+        Self { mem, transfers: 0, nack: false }
+    }
+}
+
+impl<const AI: bool> RegisterBus for FakeBus<AI> {
+    type Error = Nack;
+    type Addr = u8;
+    const AUTO_INCREMENT: bool = AI;
+
+    fn read_regs(&mut self, addr: u8, buf: &mut [u8]) -> Result<(), Nack> {
+        if self.nack {
+            return Err(Nack);
+        }
+        self.transfers += 1;
+        let addr = addr as usize;
+        buf.copy_from_slice(&self.mem[addr..addr + buf.len()]);
+        Ok(())
+    }
+
+    fn write_regs(&mut self, addr: u8, data: &[u8]) -> Result<(), Nack> {
+        if self.nack {
+            return Err(Nack);
+        }
+        self.transfers += 1;
+        let addr = addr as usize;
+        self.mem[addr..addr + data.len()].copy_from_slice(data);
+        Ok(())
+    }
+}
+
+reg_bitfields! {
+    CTRL(u8) [
+        MODE [ 0 => 2 ] {
+            Sleep = 0b00,
+            Normal = 0b11
+        },
+        OSRS [ 2 => 3 ]
+    ]
+}
+
+bus_registers! {
+    SensorRegs {
+        ( 0x10 => THRES: WO<u16> ),
+        ( 0xd0 => ID: RO<u8> ),
+        ( 0xf3 => STATUS: RC<u8> ),
+        ( 0xf4 => CTRL: RW<u8, CTRL::Reg> ),
+        ( 0xfa => TEMP: RO<u16> ),
+    }
+}
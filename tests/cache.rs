@@ -0,0 +1,152 @@
+use re_reg::prelude::*;
+
+type Cached = RegCache<FakeBus<true>, PmicRegs, 4>;
+
+#[test]
+fn test_cached_put_back() {
+    let mut dev = BusDevice::<_, PmicRegs>::new(Cached::new(FakeBus::new()));
+
+    dev.reg(PmicRegs::CTRL).try_put_back(CTRL::VSEL.val(0b101)).unwrap();
+    dev.reg(PmicRegs::CTRL).try_set_back(CTRL::EN).unwrap();
+    dev.reg(PmicRegs::CTRL).try_clear(CTRL::SLEEP).unwrap();
+    assert_eq!(dev.reg(PmicRegs::CTRL).try_read(), Ok(0b1011_0101));
+    // Only the first read reaches the bus, writes wait for sync().
+    assert_eq!(dev.bus_mut().bus_mut().counts(), (1, 0));
+    assert_eq!(dev.bus_mut().bus_mut().mem[0x10], 0x70);
+
+    assert!(dev.bus_mut().is_dirty());
+    dev.bus_mut().sync().unwrap();
+    assert!(!dev.bus_mut().is_dirty());
+    assert_eq!(dev.bus_mut().bus_mut().counts(), (1, 1));
+    assert_eq!(dev.bus_mut().bus_mut().mem[0x10], 0b1011_0101);
+}
+
+#[test]
+fn test_volatile_not_cached() {
+    let mut dev = BusDevice::<_, PmicRegs>::new(Cached::new(FakeBus::new()));
+
+    dev.bus_mut().bus_mut().mem[0x20] = 0x01;
+    assert_eq!(dev.reg(PmicRegs::STATUS).try_read(), Ok(0x01));
+    dev.bus_mut().bus_mut().mem[0x20] = 0x02;
+    assert_eq!(dev.reg(PmicRegs::STATUS).try_read(), Ok(0x02));
+    dev.reg(PmicRegs::IRQ_CLR).try_write(0xff).unwrap();
+    assert_eq!(dev.bus_mut().bus_mut().counts(), (2, 1));
+    assert!(!dev.bus_mut().is_dirty());
+}
+
+#[test]
+fn test_mark_dirty_all_after_reset() {
+    let mut dev = BusDevice::<_, PmicRegs>::new(Cached::new(FakeBus::new()));
+
+    dev.reg(PmicRegs::CTRL).try_write(0x81).unwrap();
+    dev.reg(PmicRegs::LIMIT).try_write(0x1234).unwrap();
+    dev.bus_mut().sync().unwrap();
+
+    // The device resets and loses its state.
+    dev.bus_mut().bus_mut().mem = [0; 64];
+    dev.bus_mut().mark_dirty_all();
+    dev.bus_mut().sync().unwrap();
+    assert_eq!(dev.bus_mut().bus_mut().mem[0x10], 0x81);
+    assert_eq!(&dev.bus_mut().bus_mut().mem[0x12..0x14], &[0x12, 0x34]);
+}
+
+#[test]
+fn test_bypass() {
+    let mut dev = BusDevice::<_, PmicRegs>::new(Cached::new(FakeBus::new()));
+
+    assert_eq!(dev.reg(PmicRegs::CTRL).try_read(), Ok(0x70));
+    dev.bus_mut().bus_mut().mem[0x10] = 0x71;
+    assert_eq!(dev.reg(PmicRegs::CTRL).try_read(), Ok(0x70));
+
+    dev.bus_mut().set_bypass(true);
+    assert_eq!(dev.reg(PmicRegs::CTRL).try_read(), Ok(0x71));
+    dev.bus_mut().set_bypass(false);
+    assert_eq!(dev.reg(PmicRegs::CTRL).try_read(), Ok(0x70));
+    assert_eq!(dev.bus_mut().read_uncached(PmicRegs::CTRL), Ok(0x71));
+
+    // A write while bypassed replaces the dirty cached value.
+    dev.reg(PmicRegs::CTRL).try_write(0x81).unwrap();
+    dev.bus_mut().set_bypass(true);
+    dev.reg(PmicRegs::CTRL).try_write(0x55).unwrap();
+    dev.bus_mut().set_bypass(false);
+    assert_eq!(dev.reg(PmicRegs::CTRL).try_read(), Ok(0x55));
+    assert!(!dev.bus_mut().is_dirty());
+    dev.bus_mut().sync().unwrap();
+    assert_eq!(dev.bus_mut().bus_mut().mem[0x10], 0x55);
+}
+
+#[test]
+fn test_no_auto_increment() {
+    let mut dev = BusDevice::<_, PmicRegs>::new(RegCache::<FakeBus<false>, PmicRegs, 4>::new(FakeBus::new()));
+
+    // Both bytes of LIMIT are cached, and written together on sync().
+    dev.reg(PmicRegs::LIMIT).try_write(0x1234).unwrap();
+    assert_eq!(dev.reg(PmicRegs::LIMIT).try_read(), Ok(0x1234));
+    assert_eq!(dev.bus_mut().bus_mut().counts(), (0, 0));
+    dev.bus_mut().sync().unwrap();
+    assert_eq!(dev.bus_mut().bus_mut().counts(), (0, 2));
+    assert_eq!(&dev.bus_mut().bus_mut().mem[0x12..0x14], &[0x12, 0x34]);
+
+    // RC registers are never cached.
+    dev.bus_mut().bus_mut().mem[0x22] = 0x3;
+    assert_eq!(dev.reg(PmicRegs::ISR).take().map(|isr| isr.read()), Ok(0x3));
+    assert_eq!(dev.reg(PmicRegs::ISR).take().map(|isr| isr.read()), Ok(0x3));
+    assert_eq!(dev.bus_mut().bus_mut().counts(), (2, 2));
+}
+
+struct FakeBus<const AI: bool> {
+    mem: [u8; 64],
+    reads: usize,
+    writes: usize
+}
+
+impl<const AI: bool> FakeBus<AI> {
+    fn new() -> Self {
+        let mut mem = [0; 64];
+        mem[0x10] = 0x70;
+        Self { mem, reads: 0, writes: 0 }
+    }
+
+    fn counts(&self) -> (usize, usize) {
+        (self.reads, self.writes)
+    }
+}
+
+impl<const AI: bool> RegisterBus for FakeBus<AI> {
+    type Error = ();
+    type Addr = u8;
+
+    const AUTO_INCREMENT: bool = AI;
+
+    fn read_regs(&mut self, addr: u8, buf: &mut [u8]) -> Result<(), ()> {
+        self.reads += 1;
+        let addr = addr as usize;
+        buf.copy_from_slice(&self.mem[addr..addr + buf.len()]);
+        Ok(())
+    }
+
+    fn write_regs(&mut self, addr: u8, data: &[u8]) -> Result<(), ()> {
+        self.writes += 1;
+        let addr = addr as usize;
+        self.mem[addr..addr + data.len()].copy_from_slice(data);
+        Ok(())
+    }
+}
+
+reg_bitfields! {
+    CTRL(u8) [
+        VSEL  [ 0 => 4 ],
+        SLEEP [ 6 => 1 ],
+        EN    [ 7 => 1 ]
+    ]
+}
+
+bus_registers! {
+    PmicRegs {
+        ( 0x10 => CTRL: RW<u8, CTRL::Reg> ),
+        ( 0x12 => LIMIT: RW<u16> ),
+        ( 0x20 => STATUS: RO<u8>, volatile ),
+        ( 0x21 => IRQ_CLR: WO<u8>, volatile ),
+        ( 0x22 => ISR: RC<u8> ),
+    }
+}
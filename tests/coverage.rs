@@ -0,0 +1,70 @@
+use re_reg::prelude::*;
+
+reg_bitfields! {
+    CR(u32) [
+        EN [ 0 => 1 ],
+        MODE [ 4 => 2 ] {
+            Slow = 0b01,
+            Fast = 0b10
+        }
+    ]
+}
+
+registers_layout! {
+    UartRegs {
+        ( 0x00 => CR: RW<u32, CR::Reg> ),
+        ( 0x04 => SR: RO<u16> ),
+        ( 0x06 => DR: WO<u16> ),
+        ( 0x08 => BR: RW_BE<u32, CR::Reg> ),
+        @END
+    }
+}
+
+fn run(mode: u32) -> CoverageReport {
+    let dev = Device::<_, UartRegs>::new(Coverage::<_, UartRegs>::new(FakeDevice::<UartRegs>::new()));
+    dev.reg(UartRegs::CR).put_back(CR::EN + CR::MODE.val(mode));
+    dev.backend().report()
+}
+
+#[test]
+fn test_coverage_report() {
+    let report = run(CR::Fast);
+    assert_eq!(report.to_string(), "\
+CR @0x0: RW
+  EN: 0x0=R 0x1=W
+  MODE: Slow=- Fast=W 0x0=R
+SR @0x4: -
+DR @0x6: -
+BR @0x8: -
+  EN:
+  MODE: Slow=- Fast=-
+named values covered: 1/4");
+    assert_eq!(report.uncovered(), [("CR", "MODE", "Slow"), ("BR", "MODE", "Slow"), ("BR", "MODE", "Fast")]);
+}
+
+#[test]
+fn test_coverage_merge() {
+    let mut report = run(CR::Fast);
+    report.merge(&run(CR::Slow));
+    assert_eq!(report.uncovered(), [("BR", "MODE", "Slow"), ("BR", "MODE", "Fast")]);
+    let cr = &report.registers[0];
+    assert!(cr.field_written(&CR::Reg::FIELDS[1], CR::Slow as u64));
+    assert!(!cr.field_read(&CR::Reg::FIELDS[1], CR::Slow as u64));
+}
+
+#[test]
+fn test_coverage_changed_fields() {
+    let dev = Device::<_, UartRegs>::new(Coverage::<_, UartRegs>::new(FakeDevice::<UartRegs>::new()));
+    let (en, mode) = (&CR::Reg::FIELDS[0], &CR::Reg::FIELDS[1]);
+
+    // Setting EN leaves MODE at 0, which doesn't count as written.
+    dev.reg(UartRegs::CR).set_back(CR::EN);
+    // BR is big-endian, its fields are decoded in logical order.
+    dev.reg(UartRegs::BR).put_back(CR::MODE.val(CR::Slow));
+    let report = dev.backend().report();
+    let (cr, br) = (&report.registers[0], &report.registers[3]);
+    assert!(cr.field_written(en, 1));
+    assert!(cr.field_read(mode, 0) && !cr.field_written(mode, 0));
+    assert!(br.field_written(mode, CR::Slow as u64));
+    assert!(!br.field_written(en, 0));
+}
@@ -0,0 +1,56 @@
+use re_reg::prelude::*;
+
+reg_bitfields! {
+    CR(u32) [
+        EN [ 0 => 1 ],
+        MODE [ 8 => 4 ]
+    ]
+}
+
+registers_layout! {
+    MixedRegs {
+        ( 0x00 => CR: RW_BE<u32, CR::Reg> ),
+        ( 0x04 => ID: RO_LE<u32> ),
+        ( 0x08 => DR: RW<u32> ),
+        @END
+    }
+}
+
+registers_layout! {
+    BigRegs(BE) {
+        ( 0x00 => CR: RW<u32, CR::Reg> ),
+        ( 0x04 => ID: RO<u32> ),
+        ( 0x08 => DR: RW_LE<u32> ),
+        @END
+    }
+}
+
+#[test]
+fn test_endian_kinds() {
+    let io = FakeDevice::<MixedRegs>::new();
+    io.poke::<u32>(0x04, 0x1234_5678u32.to_le());
+
+    io.CR.put_back(CR::EN.val(1) + CR::MODE.val(0xa));
+    assert_eq!(io.peek::<u32>(0x00), 0x0000_0a01u32.to_be());
+    assert_eq!(io.CR.read(), 0x0000_0a01);
+    assert_eq!(io.CR.get(CR::MODE), 0xa);
+    assert_eq!(io.ID.read(), 0x1234_5678);
+
+    io.DR.write(0x1122_3344);
+    assert_eq!(io.peek::<u32>(0x08), 0x1122_3344);
+}
+
+#[test]
+fn test_endian_layout_default() {
+    let io = FakeDevice::<BigRegs>::new();
+    io.poke::<u32>(0x04, 0xcafe_f00du32.to_be());
+
+    io.CR.set_back(CR::EN);
+    assert_eq!(io.peek::<u32>(0x00), 1u32.to_be());
+    assert!(io.CR.is_set(CR::EN));
+    assert_eq!(io.ID.read(), 0xcafe_f00d);
+
+    // Explicit kinds override the layout's byte order.
+    io.DR.write(0x1122_3344);
+    assert_eq!(io.peek::<u32>(0x08), 0x1122_3344u32.to_le());
+}
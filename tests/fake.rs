@@ -0,0 +1,43 @@
+use re_reg::prelude::*;
+
+reg_bitfields! {
+    CR(u32) [
+        EN [ 0 => 1 ],
+        MODE [ 4 => 2 ]
+    ]
+}
+
+registers_layout! {
+    UartRegs {
+        ( 0x00 => CR: RW<u32, CR::Reg> ),
+        ( 0x04 => _reserved ),
+        ( 0x08 => SR: RO<u16> ),
+        ( 0x0a => DR: WO<u16> ),
+        @END
+    }
+}
+
+#[test]
+fn test_fake_device() {
+    let fake = FakeDevice::<UartRegs>::new();
+
+    fake.CR.put_back(CR::EN + CR::MODE.val(2));
+    assert_eq!(fake.peek_reg(UartRegs::CR), 0x21);
+    fake.poke_reg(UartRegs::SR, 0x80);
+    assert_eq!(fake.SR.read(), 0x80);
+    fake.poke::<u32>(0x04, 0xdead_beef);
+    assert_eq!(fake.peek::<u16>(0x06), 0xdead);
+
+    // The fake is a backend too.
+    let dev = Device::<_, UartRegs>::new(fake);
+    dev.reg(UartRegs::DR).write(0x55);
+    assert_eq!(dev.backend().peek::<u16>(0x0a), 0x55);
+    assert!(dev.reg(UartRegs::CR).is_set(CR::EN));
+}
+
+#[test]
+#[should_panic(expected = "Offset out of the layout")]
+fn test_fake_out_of_bounds() {
+    let fake = FakeDevice::<UartRegs>::new();
+    fake.poke::<u32>(0x0c, 0);
+}
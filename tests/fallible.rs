@@ -0,0 +1,85 @@
+use std::cell::Cell;
+
+use re_reg::prelude::*;
+use re_reg::Error;
+
+/// Generic driver code, written once for any register.
+fn enable<U>(reg: &U, mode: u32) -> Result<(), Error<U::BusError>>
+where
+    U: TryReadWritableIO<u32, F1::Reg> + TryReadableIO<u32, F1::Reg>
+{
+    reg.try_put_back(F1::MODE.try_val(mode)? + F1::EN)?;
+    reg.try_wait_set(F1::RDY, 3)
+}
+
+#[test]
+fn test_infallible_registers() {
+    let io = FakeDevice::<RA>::new();
+    io.poke_reg(RA::VX, 1 << 4);
+
+    assert_eq!(enable(&io.VX, 0b10), Ok(()));
+    assert_eq!(io.VX.try_get(F1::MODE), Ok(0b10));
+    assert_eq!(io.VX.try_write_verified(0x12345678), Ok(()));
+    assert_eq!(io.VX.try_read(), Ok(0x12345678));
+}
+
+#[test]
+fn test_overflow() {
+    let io = FakeDevice::<RA>::new();
+    io.poke_reg(RA::VX, 1 << 4);
+
+    assert_eq!(enable(&io.VX, 0b100), Err(Error::Overflow));
+    assert_eq!(io.VX.read(), 1 << 4);
+    assert!(F1::MODE.try_val::<()>(0b11).is_ok());
+}
+
+#[test]
+fn test_timeout() {
+    let io = FakeDevice::<RA>::new();
+
+    assert_eq!(enable(&io.VX, 0b01), Err(Error::Timeout));
+    assert_eq!(io.VX.read(), 0b101);
+}
+
+#[test]
+fn test_verify_mismatch() {
+    let reg = StuckReg { val: Cell::new(0), stuck: 0x80 };
+
+    assert_eq!(reg.try_write_verified(0x01), Err(Error::VerifyMismatch { written: 0x01, read: 0x81 }));
+    assert_eq!(reg.try_write_verified(0x81), Ok(()));
+}
+
+/// A register with a bit stuck at 1.
+struct StuckReg {
+    val: Cell<u32>,
+    stuck: u32
+}
+
+impl InfallibleIO for StuckReg {}
+
+impl ReadableIO<u32> for StuckReg {
+    fn read(&self) -> u32 {
+        self.val.get() | self.stuck
+    }
+}
+
+impl WritableIO<u32> for StuckReg {
+    fn write(&self, val: u32) {
+        self.val.set(val);
+    }
+}
+
+reg_bitfields! {
+    F1(u32) [
+        MODE [ 0 => 2 ],
+        EN   [ 2 => 1 ],
+        RDY  [ 4 => 1 ]
+    ]
+}
+
+registers_layout! {
+    RA {
+        ( 0x00 => VX: RW<u32, F1::Reg> ),
+        @END
+    }
+}
@@ -0,0 +1,74 @@
+use std::cell::{Cell, RefCell};
+
+use re_reg::prelude::*;
+
+#[test]
+fn test_indirect_access() {
+    let io = FakeDevice::<MdioRegs>::new();
+    let space = IndirectSpace::<_, _, PhyRegs>::new(&io.INDEX, &io.DATA);
+
+    io.poke_reg(MdioRegs::DATA, 0x0022);
+    assert_eq!(space.reg(PhyRegs::PHYID1).read(), 0x0022);
+    assert_eq!(io.peek_reg(MdioRegs::INDEX), 0x02);
+
+    io.poke_reg(MdioRegs::DATA, 0x0140);
+    space.reg(PhyRegs::BMCR).put_back(BMCR::SPEED.val(1) + BMCR::AN.val(1));
+    assert_eq!(io.peek_reg(MdioRegs::INDEX), 0x00);
+    assert_eq!(io.peek_reg(MdioRegs::DATA), 0x3140);
+    space.reg(PhyRegs::BMCR).clear(BMCR::AN);
+    assert!(space.reg(PhyRegs::BMCR).is_set(BMCR::SPEED));
+    assert_eq!(io.peek_reg(MdioRegs::DATA), 0x2140);
+}
+
+#[test]
+fn test_indirect_lock() {
+    let io = FakeDevice::<MdioRegs>::new();
+    let lock = PhyLock { io: &io, sections: RefCell::new(Vec::new()), locked: Cell::new(false) };
+    let space = IndirectSpace::<_, _, PhyRegs>::new(&io.INDEX, &io.DATA).with_lock(&lock);
+
+    io.poke_reg(MdioRegs::INDEX, 0x1f);
+    space.reg(PhyRegs::BMCR).set_back(BMCR::RESET);
+    assert_eq!(io.peek_reg(MdioRegs::DATA), 0x8000);
+    // One section for the read, one for the write, each covering INDEX + DATA.
+    assert_eq!(*lock.sections.borrow(), [(0x1f, 0x00), (0x00, 0x00)]);
+}
+
+/// Records INDEX when entering each section and INDEX after it.
+struct PhyLock<'a> {
+    io: &'a FakeDevice<MdioRegs>,
+    sections: RefCell<Vec<(u8, u8)>>,
+    locked: Cell<bool>
+}
+
+impl CriticalSection for &PhyLock<'_> {
+    fn with_lock(&self, f: &mut dyn FnMut()) {
+        assert!(!self.locked.replace(true));
+        let before = self.io.peek_reg(MdioRegs::INDEX);
+        f();
+        self.sections.borrow_mut().push((before, self.io.peek_reg(MdioRegs::INDEX)));
+        self.locked.set(false);
+    }
+}
+
+reg_bitfields! {
+    BMCR(u16) [
+        AN    [ 12 => 1 ],
+        SPEED [ 13 => 1 ],
+        RESET [ 15 => 1 ]
+    ]
+}
+
+registers_layout! {
+    MdioRegs {
+        ( 0x00 => INDEX: WO<u8> ),
+        ( 0x02 => DATA: RW<u16> ),
+        @END
+    }
+}
+
+indirect_registers! {
+    PhyRegs {
+        ( 0x00 => BMCR: RW<u16, BMCR::Reg> ),
+        ( 0x02 => PHYID1: RO<u16> ),
+    }
+}
@@ -0,0 +1,52 @@
+use re_reg::prelude::*;
+
+reg_bitfields! {
+    CR(u32) [
+        EN [ 0 => 1 ],
+        PRIO [ 16 => 4 ],
+        DIV [ 12 => 8 ]
+    ]
+}
+
+registers_layout! {
+    Regs {
+        ( 0x00 => CR: RW<u32, CR::Reg> ),
+        ( 0x04 => BE: RW_BE<u32, CR::Reg> ),
+        @END
+    }
+}
+
+#[test]
+fn test_lane_access() {
+    let io = FakeDevice::<Regs>::new();
+    io.poke_reg(Regs::CR, 0x4433_2211);
+
+    assert_eq!(io.CR.read_lane::<u8>(2), 0x33);
+    assert_eq!(io.CR.read_lane::<u16>(1), 0x4433);
+    io.CR.write_lane::<u8>(2, 0xaa);
+    assert_eq!(io.peek::<u32>(0x00).to_ne_bytes(), [0x11, 0x22, 0xaa, 0x44]);
+
+    // Lane 0 is the least significant byte whatever the byte order.
+    io.BE.write_lane::<u8>(0, 0x5a);
+    assert_eq!(io.BE.read(), 0x5a);
+    assert_eq!(io.peek::<u32>(0x04).to_ne_bytes(), [0, 0, 0, 0x5a]);
+}
+
+#[test]
+fn test_put_narrow() {
+    let io = FakeDevice::<Regs>::new();
+    io.poke_reg(Regs::CR, 0x4433_2211);
+
+    // PRIO lies in byte 2, only that byte is rewritten.
+    io.CR.put_narrow::<CR::PRIO>(0xf);
+    assert_eq!(io.peek::<u32>(0x00).to_ne_bytes(), [0x11, 0x22, 0x3f, 0x44]);
+    // DIV spans bytes 1 and 2, so the low halfword is too small.
+    io.CR.put_narrow::<CR::DIV>(0x00);
+    assert_eq!(io.peek::<u32>(0x00).to_ne_bytes(), [0x11, 0x02, 0x30, 0x44]);
+    assert_eq!(io.CR.get(CR::PRIO), 0x0);
+
+    io.BE.put_narrow::<CR::EN>(1);
+    assert_eq!(io.peek::<u32>(0x04).to_ne_bytes(), [0, 0, 0, 1]);
+    io.BE.put_lane::<u16, CR::PRIO>(0x5);
+    assert_eq!(io.peek::<u32>(0x04).to_ne_bytes(), [0, 0x05, 0, 1]);
+}
@@ -0,0 +1,108 @@
+use std::cell::Cell;
+
+use re_reg::prelude::*;
+
+reg_bitfields! {
+    CR(u32) [
+        EN [ 0 => 1 ],
+        MODE [ 4 => 4 ]
+    ]
+}
+
+registers_layout! {
+    Regs {
+        ( 0x00 => CR: RW<u32, CR::Reg> ),
+        ( 0x04 => DR: RW<u32> ),
+        @END
+    }
+}
+
+/// Counts the sections, and checks they don't nest.
+#[derive(Default)]
+struct CountingLock {
+    sections: Cell<usize>,
+    inside: Cell<bool>
+}
+
+impl CriticalSection for CountingLock {
+    fn with_lock(&self, f: &mut dyn FnMut()) {
+        assert!(!self.inside.replace(true));
+        self.sections.set(self.sections.get() + 1);
+        f();
+        self.inside.set(false);
+    }
+}
+
+#[test]
+fn test_locked_register() {
+    let io = FakeDevice::<Regs>::new();
+    let cs = CountingLock::default();
+
+    let cr = io.CR.locked(&cs);
+    cr.put_back(CR::MODE.val(0x5));
+    cr.set_back(CR::EN);
+    cr.clear(CR::EN);
+    cr.transaction(|tx| {
+        tx.set(CR::EN).put(CR::MODE.val(0xa));
+    });
+    assert_eq!(cr.read(), 0xa1);
+    cr.reg().write(0);
+    assert_eq!(cs.sections.get(), 4);
+}
+
+#[test]
+fn test_mutex_lock() {
+    let fake = FakeDevice::<Regs>::new();
+    let addr = fake.regs() as *const Regs as usize;
+    let lock = MutexLock::new();
+
+    std::thread::scope(|s| {
+        for bit in 0..8 {
+            let lock = &lock;
+            s.spawn(move || {
+                // SAFETY: the layout lives in `fake` until the scope ends, and its
+                // memory is interior-mutable.
+                let io = unsafe { &*(addr as *const Regs) };
+                for _ in 0..1000 {
+                    io.DR.locked(lock).set_back(Bits::new(bit, 1 << bit));
+                    io.DR.locked(lock).clear(Bits::new(bit, 1 << bit));
+                }
+                io.DR.locked(lock).set_back(Bits::new(bit, 1 << bit));
+            });
+        }
+    });
+    assert_eq!(fake.peek_reg(Regs::DR), 0xff);
+}
+
+#[cfg(feature = "locked-rmw")]
+#[test]
+fn test_global_lock() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static SECTIONS: AtomicUsize = AtomicUsize::new(0);
+
+    fn hook(f: &mut dyn FnMut()) {
+        SECTIONS.fetch_add(1, Ordering::Relaxed);
+        f();
+    }
+
+    let io = FakeDevice::<Regs>::new();
+    assert!(set_global_lock(hook).is_ok());
+    assert!(set_global_lock(hook).is_err());
+
+    io.CR.set_back(CR::EN);
+    io.CR.put_back(CR::MODE.val(3));
+    io.CR.write(0);
+    assert_eq!(SECTIONS.load(Ordering::Relaxed), 2);
+
+    // The other read-modify-writes take the lock too.
+    io.CR.try_clear(CR::EN).unwrap();
+    io.CR.try_transaction(|tx| { tx.set(CR::EN); }).unwrap();
+    io.CR.put_narrow::<CR::MODE>(5);
+    io.transaction(|tx| {
+        tx.set(Regs::CR, CR::EN);
+        tx.put(Regs::DR, Bits::new(0, 0xff).val(0x12));
+    });
+    assert_eq!(SECTIONS.load(Ordering::Relaxed), 7);
+    assert_eq!((io.peek_reg(Regs::CR), io.peek_reg(Regs::DR)), (0x51, 0x12));
+}
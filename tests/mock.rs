@@ -0,0 +1,68 @@
+use re_reg::prelude::*;
+
+reg_bitfields! {
+    CR(u32) [
+        EN [ 0 => 1 ],
+        DLEN [ 4 => 2 ] {
+            DLen7 = 0b01,
+            DLen8 = 0b10
+        }
+    ]
+}
+
+registers_layout! {
+    UartRegs {
+        ( 0x00 => CR: RW<u32, CR::Reg> ),
+        ( 0x04 => SR: RO<u16> ),
+        ( 0x06 => DR: WO<u16> ),
+        @END
+    }
+}
+
+fn init<B: RegisterBackend>(dev: &Device<B, UartRegs>, dlen: u32) {
+    dev.reg(UartRegs::CR).write(0x100);
+    dev.reg(UartRegs::CR).put_back(CR::EN + CR::DLEN.val(dlen));
+    while dev.reg(UartRegs::SR).read() & 0x20 == 0 {}
+}
+
+#[test]
+fn test_mock_expectations() {
+    let mock = MockDevice::<UartRegs>::new();
+    mock.expect_write(UartRegs::CR, CR::DLEN.val(0))
+        .expect_read(UartRegs::CR, 0x100)
+        .expect_write(UartRegs::CR, CR::EN + CR::DLEN.val(CR::DLen8))
+        .expect_read(UartRegs::SR, 0x00)
+        .expect_read(UartRegs::SR, 0x20);
+
+    let dev = Device::<_, UartRegs>::new(mock);
+    init(&dev, CR::DLen8);
+    dev.backend().expect_write_exact(UartRegs::DR, 0x55);
+    dev.reg(UartRegs::DR).write(0x55);
+    dev.backend().verify();
+}
+
+#[test]
+#[should_panic(expected = "unexpected value in W CR @0x0 (32-bit) = 0x00000111 [EN=0x1, DLEN=DLen7]\n  \
+                           expected W CR @0x0 with EN=0x1 DLEN=DLen8\n  DLEN: expected DLen8, got DLen7")]
+fn test_mock_wrong_value() {
+    let mock = MockDevice::<UartRegs>::new();
+    mock.expect_write(UartRegs::CR, CR::EN.val(0))
+        .expect_read(UartRegs::CR, 0x100)
+        .expect_write(UartRegs::CR, CR::EN + CR::DLEN.val(CR::DLen8));
+    init(&Device::<_, UartRegs>::new(mock), CR::DLen7);
+}
+
+#[test]
+#[should_panic(expected = "unexpected access R CR @0x0 (32-bit)")]
+fn test_mock_unexpected_access() {
+    let mock = MockDevice::<UartRegs>::new();
+    mock.expect_write(UartRegs::CR, CR::EN.val(0));
+    init(&Device::<_, UartRegs>::new(mock), CR::DLen7);
+}
+
+#[test]
+#[should_panic(expected = "expected access R SR @0x4 returning 0x20 not met")]
+fn test_mock_unmet() {
+    let mock = MockDevice::<UartRegs>::new();
+    mock.expect_read(UartRegs::SR, 0x20);
+}
@@ -65,6 +65,7 @@ fn get_input() -> u32 {
 
 trait RegLike {
     fn p(self, input: u32, off: usize, mask: u32) -> Self;
+    #[allow(dead_code)]
     fn cl(self, mask1: u32, mask2: u32) -> Self;
 }
 
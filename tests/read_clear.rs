@@ -0,0 +1,43 @@
+use re_reg::prelude::*;
+
+#[test]
+fn test_take() {
+    let io = FakeDevice::<RC1>::new();
+    io.poke_reg(RC1::ISR, 0b10110);
+
+    let isr = io.ISR.take();
+    assert_eq!(isr.read(), 0b10110);
+    assert_eq!(isr.get(I1::CNT), 0b101);
+    assert!(!isr.is_set(I1::ERR));
+    assert!(isr.is_set(I1::OVR));
+}
+
+#[test]
+fn test_take_data() {
+    let io = FakeDevice::<RC1>::new();
+    io.poke_reg(RC1::DR, 0x1a5);
+
+    let data = io.DR.take();
+    assert_eq!(data.get(I2::DATA), 0xa5);
+    assert!(data.is_set(I2::PE));
+}
+
+reg_bitfields! {
+    I1(u32) [
+        ERR [ 0 => 1 ],
+        OVR [ 1 => 1 ],
+        CNT [ 2 => 3 ]
+    ],
+    I2(u16) [
+        DATA [ 0 => 8 ],
+        PE   [ 8 => 1 ]
+    ]
+}
+
+registers_layout! {
+    RC1 {
+        ( 0x00 => ISR: RC<u32, I1::Reg> ),
+        ( 0x04 => DR: RC<u16, I2::Reg> ),
+        @END
+    }
+}
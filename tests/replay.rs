@@ -0,0 +1,83 @@
+use re_reg::prelude::*;
+
+reg_bitfields! {
+    CR(u32) [
+        EN [ 0 => 1 ],
+        MODE [ 4 => 2 ] {
+            Slow = 0b01,
+            Fast = 0b10
+        }
+    ]
+}
+
+registers_layout! {
+    UartRegs {
+        ( 0x00 => CR: RW<u32, CR::Reg> ),
+        ( 0x04 => SR: RO<u16> ),
+        ( 0x06 => DR: WO<u16> ),
+        ( 0x08 => BR: RW_BE<u32, CR::Reg> ),
+        @END
+    }
+}
+
+fn init<B: RegisterBackend>(dev: &Device<B, UartRegs>, mode: u32) {
+    dev.reg(UartRegs::CR).put_back(CR::EN + CR::MODE.val(mode));
+    if dev.reg(UartRegs::SR).read() & 0x1 != 0 {
+        dev.reg(UartRegs::DR).write(0x55);
+    }
+}
+
+fn record() -> Script {
+    let fake = FakeDevice::<UartRegs>::new();
+    fake.poke_reg(UartRegs::SR, 0x1);
+    let dev = Device::<_, UartRegs>::new(Recorder::new(fake));
+    init(&dev, CR::Fast);
+    dev.backend().script()
+}
+
+#[test]
+fn test_record_replay() {
+    let script = record();
+    let text = script.to_string();
+    assert_eq!(text, "R 0x00 4 0x00000000\nW 0x00 4 0x00000021\nR 0x04 2 0x0001\nW 0x06 2 0x0055\n");
+    assert_eq!(Script::parse(&format!("# init\n\n{}", text)).unwrap(), script);
+
+    let dev = Device::<_, UartRegs>::new(Replayer::<UartRegs>::new(script));
+    init(&dev, CR::Fast);
+    // Dropping the replayer checks no access is missing.
+    drop(dev);
+}
+
+#[test]
+#[should_panic(expected = "expected W CR @0x0 (32-bit) = 0x00000021 [EN=0x1, MODE=Fast]\n  \
+                           got      W CR @0x0 (32-bit) = 0x00000011 [EN=0x1, MODE=Slow]\n  MODE: Fast != Slow")]
+fn test_replay_divergence() {
+    let dev = Device::<_, UartRegs>::new(Replayer::<UartRegs>::new(record()));
+    init(&dev, CR::Slow);
+}
+
+#[test]
+#[should_panic(expected = "expected W BR @0x8 (32-bit) = 0x00000021 [EN=0x1, MODE=Fast]\n  \
+                           got      W BR @0x8 (32-bit) = 0x00000011 [EN=0x1, MODE=Slow]\n  MODE: Fast != Slow")]
+fn test_replay_big_endian() {
+    // The script holds the hardware byte order, the fields are decoded in logical order.
+    let script = Script::parse(&format!("W 0x08 4 {:#x}", 0x21u32.to_be())).unwrap();
+    let dev = Device::<_, UartRegs>::new(Replayer::<UartRegs>::new(script));
+    dev.reg(UartRegs::BR).write(0x11);
+}
+
+#[test]
+#[should_panic(expected = "register access 2 missing")]
+fn test_replay_missing() {
+    let dev = Device::<_, UartRegs>::new(Replayer::<UartRegs>::new(record()));
+    dev.reg(UartRegs::CR).put_back(CR::EN + CR::MODE.val(CR::Fast));
+}
+
+#[test]
+fn test_script_parse_error() {
+    let err = Script::parse("R 0x00 4 0x0\nX 0x04 2 0x1\n").unwrap_err();
+    assert_eq!(err.line, 2);
+    assert!(Script::parse("W 0x00 3 0x0").is_err());
+    // The value doesn't fit the width.
+    assert!(Script::parse("R 0x00 1 0x1234").is_err());
+}
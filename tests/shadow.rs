@@ -0,0 +1,52 @@
+use re_reg::prelude::*;
+
+#[test]
+fn test_shadow_put_back() {
+    let io = FakeDevice::<RS>::new();
+    let wx = io.WX.shadowed(0);
+
+    wx.put_back(S1::B1.val(0b11) + S1::B3);
+    wx.put_back(S1::B2.val(0b10));
+    assert_eq!(wx.shadow(), 0b01101011);
+    assert_eq!(io.peek_reg(RS::WX), 0b01101011);
+
+    wx.clear(S1::B1);
+    wx.set_back(S1::B2);
+    assert_eq!(wx.shadow(), 0b01101100);
+    assert_eq!(io.peek_reg(RS::WX), 0b01101100);
+}
+
+#[test]
+fn test_shadow_write_and_sync() {
+    let io = FakeDevice::<RS>::new();
+    io.poke_reg(RS::WY, 0xff);
+    let wy = io.WY.shadowed(0xff);
+
+    wy.put(S2::B1.val(0b1));
+    assert_eq!(wy.shadow(), 0b1);
+    assert_eq!(io.peek_reg(RS::WY), 0b1);
+
+    // The device lost its state, restore it from the shadow.
+    io.poke_reg(RS::WY, 0);
+    wy.sync();
+    assert_eq!(io.peek_reg(RS::WY), 0b1);
+}
+
+reg_bitfields! {
+    S1(u32) [
+        B1 [ 0 => 2 ],
+        B2 [ 2 => 2 ],
+        B3 [ 5 => 2 ]
+    ],
+    S2(u16) [
+        B1 [ 0 => 1 ]
+    ]
+}
+
+registers_layout! {
+    RS {
+        ( 0x00 => WX: WOS<u32, S1::Reg> ),
+        ( 0x04 => WY: WOS<u16, S2::Reg> ),
+        @END
+    }
+}
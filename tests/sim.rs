@@ -0,0 +1,72 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+
+use re_reg::prelude::*;
+
+reg_bitfields! {
+    SR(u32) [
+        RXNE [ 0 => 1 ],
+        READY [ 1 => 1 ],
+        OVR [ 4 => 1 ]
+    ]
+}
+
+registers_layout! {
+    UartRegs {
+        ( 0x00 => SR: RW<u32, SR::Reg> ),
+        ( 0x04 => DR: RW<u32> ),
+        @END
+    }
+}
+
+/// A UART receiving `rx`, ready after 3 polls of SR, with a W1C overrun flag.
+fn uart<'a>(sim: &mut SimDevice<'a, UartRegs>, rx: &'a RefCell<VecDeque<u32>>, polls: &'a Cell<u32>) {
+    sim.on_read(UartRegs::SR, move |_, val| {
+        polls.set(polls.get() + 1);
+        let ready = if polls.get() >= 3 { 0b10 } else { 0 };
+        let rxne = !rx.borrow().is_empty() as u32;
+        val & !0b11 | ready | rxne
+    });
+    sim.on_read(UartRegs::DR, move |_, _| rx.borrow_mut().pop_front().unwrap_or(0));
+    sim.w1c(UartRegs::SR);
+}
+
+#[test]
+fn test_sim_poll_and_fifo() {
+    let rx = RefCell::new(VecDeque::from([0x41, 0x42]));
+    let polls = Cell::new(0);
+    let mut sim = SimDevice::new();
+    uart(&mut sim, &rx, &polls);
+    let dev = Device::<_, UartRegs>::new(sim);
+
+    while !dev.reg(UartRegs::SR).is_set(SR::READY) {}
+    assert_eq!(polls.get(), 3);
+
+    let mut got = Vec::new();
+    while dev.reg(UartRegs::SR).is_set(SR::RXNE) {
+        got.push(dev.reg(UartRegs::DR).read());
+    }
+    assert_eq!(got, [0x41, 0x42]);
+    assert!(rx.borrow().is_empty());
+}
+
+#[test]
+fn test_sim_w1c() {
+    let rx = RefCell::new(VecDeque::new());
+    let polls = Cell::new(0);
+    let mut sim = SimDevice::new();
+    uart(&mut sim, &rx, &polls);
+    sim.mem().poke_reg(UartRegs::SR, 0x10);
+    let dev = Device::<_, UartRegs>::new(sim);
+
+    assert!(dev.reg(UartRegs::SR).is_set(SR::OVR));
+    // Writing 0 leaves the flag, writing 1 clears it.
+    dev.reg(UartRegs::SR).write(0);
+    assert!(dev.reg(UartRegs::SR).is_set(SR::OVR));
+    dev.reg(UartRegs::SR).set(SR::OVR);
+    assert!(!dev.reg(UartRegs::SR).is_set(SR::OVR));
+
+    // Plain registers just store the value.
+    dev.reg(UartRegs::DR).write(7);
+    assert_eq!(dev.backend().mem().peek_reg(UartRegs::DR), 7);
+}
@@ -0,0 +1,101 @@
+use std::cell::{Cell, RefCell};
+
+use re_reg::prelude::*;
+
+reg_bitfields! {
+    CMP(u64) [
+        EN [ 0 => 1 ],
+        VAL [ 28 => 8 ]
+    ]
+}
+
+registers_layout! {
+    TimerRegs {
+        ( 0x00 => CNT: SPLIT<u64, u32, HighFirst> ),
+        ( 0x08 => CMP: SPLIT<u64, u32, LowFirst, CMP::Reg> ),
+        @END
+    }
+}
+
+registers_layout! {
+    BigTimerRegs(BE) {
+        ( 0x00 => CNT: SPLIT<u64, u32, HighFirst> ),
+        @END
+    }
+}
+
+#[test]
+fn test_split_tear_free_read() {
+    // The counter carries from the low half into the high half during the read.
+    let dev = Device::<_, TimerRegs>::new(Counter::new(0xffff_ffff));
+
+    assert_eq!(dev.reg(TimerRegs::CNT).read(), 0x1_0000_0002);
+    assert_eq!(*dev.backend().log.borrow(), [
+        "read 4", "read 0", "read 4", "read 0", "read 4"
+    ]);
+}
+
+#[test]
+fn test_split_write_order() {
+    let dev = Device::<_, TimerRegs>::new(Counter::new(0));
+
+    dev.reg(TimerRegs::CNT).write(0x1122_3344_5566_7788);
+    dev.reg(TimerRegs::CMP).write(0);
+    assert_eq!(*dev.backend().log.borrow(), [
+        "write 4 11223344", "write 0 55667788", "write 8 0", "write c 0"
+    ]);
+}
+
+#[test]
+fn test_split_fields() {
+    let io = FakeDevice::<TimerRegs>::new();
+
+    // The field spans both halves.
+    io.CMP.put_back(CMP::EN.val(1) + CMP::VAL.val(0xab));
+    assert_eq!(io.peek::<u32>(0x08), 0xb000_0001);
+    assert_eq!(io.peek::<u32>(0x0c), 0x0000_000a);
+    assert_eq!(io.CMP.get(CMP::VAL), 0xab);
+    assert_eq!(core::mem::size_of::<TimerRegs>(), 16);
+}
+
+#[test]
+fn test_split_big_endian() {
+    let io = FakeDevice::<BigTimerRegs>::new();
+
+    // The high half goes at the lower address, the register is big-endian as a whole.
+    io.CNT.write(0x1122_3344_5566_7788);
+    assert_eq!(io.peek::<u64>(0x00), 0x1122_3344_5566_7788u64.to_be());
+    assert_eq!(io.CNT.read(), 0x1122_3344_5566_7788);
+
+    let dev = Device::<_, BigTimerRegs>::new(Counter::new(0));
+    dev.reg(BigTimerRegs::CNT).write(0x1122_3344_5566_7788);
+    assert_eq!(*dev.backend().log.borrow(), ["write 0 44332211", "write 4 88776655"]);
+}
+
+/// A free-running counter at 0x00, counting on every access, and plain memory above.
+struct Counter {
+    count: Cell<u64>,
+    log: RefCell<Vec<String>>
+}
+
+impl Counter {
+    fn new(count: u64) -> Self {
+        Self {
+            count: Cell::new(count),
+            log: RefCell::new(Vec::new())
+        }
+    }
+}
+
+impl RegisterBackend for Counter {
+    fn read<T: UIntLike>(&self, offset: usize) -> T {
+        self.log.borrow_mut().push(format!("read {:x}", offset));
+        let count = self.count.get();
+        self.count.set(count + 1);
+        T::from_u64(count >> (offset * 8))
+    }
+
+    fn write<T: UIntLike>(&self, offset: usize, val: T) {
+        self.log.borrow_mut().push(format!("write {:x} {:x}", offset, val.to_u64()));
+    }
+}
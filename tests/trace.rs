@@ -0,0 +1,77 @@
+use re_reg::prelude::*;
+
+reg_bitfields! {
+    CR(u32) [
+        EN [ 0 => 1 ],
+        MODE [ 4 => 2 ] {
+            Slow = 0b01,
+            Fast = 0b10
+        }
+    ]
+}
+
+registers_layout! {
+    UartRegs {
+        ( 0x00 => CR: RW<u32, CR::Reg> ),
+        ( 0x04 => DR: WO<u16> ),
+        ( 0x06 => _reserved0 ),
+        ( 0x08 => CR2: RW<u32, CR::Reg> ),
+        ( 0x0c => _reserved1 ),
+        ( 0x10 => SCR: SCT<u32, CR::Reg> ),
+        ( 0x20 => CNT: SPLIT<u64, u32, LowFirst> ),
+        @END
+    }
+}
+
+#[test]
+fn test_access_display() {
+    let access = Access {
+        name: "CR",
+        offset: 0x10,
+        width: 4,
+        value: 0x21,
+        direction: Direction::Write,
+        fields: <CR::Reg as RegName>::FIELDS
+    };
+    assert_eq!(access.to_string(), "W CR @0x10 (32-bit) = 0x00000021 [EN=0x1, MODE=Fast]");
+
+    let access = Access { name: "", width: 2, value: 0x7, direction: Direction::Read, fields: &[], ..access };
+    assert_eq!(access.to_string(), "R ? @0x10 (16-bit) = 0x0007");
+}
+
+#[cfg(feature = "trace")]
+#[test]
+fn test_tracer() {
+    use std::sync::Mutex;
+
+    static LOG: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    fn tracer(access: &Access) {
+        LOG.lock().unwrap().push(format!("{:?} {} @{:#x} {:#x}", access.direction, access.name, access.offset, access.value));
+    }
+
+    let fake: &'static FakeDevice<UartRegs> = Box::leak(Box::new(FakeDevice::new()));
+    set_tracer(tracer);
+    fake.CR.put_back(CR::MODE.val(CR::Fast));
+    // Without the layout, the access is only known by its address.
+    let addr = &fake.CR as *const _ as usize;
+    assert_eq!(LOG.lock().unwrap()[0], format!("Read  @{:#x} 0x0", addr));
+
+    LOG.lock().unwrap().clear();
+    assert!(add_layout(fake.regs()).is_ok());
+    fake.CR.put_back(CR::MODE.val(CR::Fast));
+    fake.DR.write(0x55);
+    fake.CR2.set_back(CR::EN);
+    fake.CR2.write_lane::<u8>(1, 0x12);
+    fake.CR2.put_lane::<u8, CR::MODE>(CR::Slow);
+    assert_eq!(*LOG.lock().unwrap(), [
+        "Read CR @0x0 0x20", "Write CR @0x0 0x20", "Write DR @0x4 0x55", "Read CR2 @0x8 0x0", "Write CR2 @0x8 0x1",
+        "Write CR2 @0x9 0x12", "Read CR2 @0x8 0x1", "Write CR2 @0x8 0x11"
+    ]);
+
+    // Aliases and halves are traced as parts of their register.
+    LOG.lock().unwrap().clear();
+    fake.SCR.set_back(CR::EN);
+    fake.CNT.write(0x1_0000_0002);
+    assert_eq!(*LOG.lock().unwrap(), ["Write SCR @0x14 0x1", "Write CNT @0x20 0x2", "Write CNT @0x24 0x1"]);
+}
@@ -0,0 +1,88 @@
+use std::cell::Cell;
+
+use re_reg::prelude::*;
+
+#[test]
+fn test_transaction() {
+    let reg = CountingReg { val: Cell::new(0xf0), reads: Cell::new(0), writes: Cell::new(0) };
+
+    reg.transaction(|tx| {
+        tx.put(F1::B1.val(0b10));
+        tx.set(F1::B2);
+        tx.clear(F1::HI);
+        tx.put(F1::B1.val(0b01));
+    });
+    assert_eq!(reg.val.get(), 0b1101);
+    assert_eq!((reg.reads.get(), reg.writes.get()), (1, 1));
+
+    reg.transaction(|_| {});
+    assert_eq!((reg.reads.get(), reg.writes.get()), (1, 1));
+}
+
+#[test]
+fn test_layout_transaction() {
+    let io = FakeDevice::<RA>::new();
+    io.poke_reg(RA::VX, 0xff00);
+    io.poke_reg(RA::VY, 0x0f);
+
+    io.transaction(|tx| {
+        tx.put(RA::VX, F1::B1.val(0b11));
+        tx.set(RA::VY, F2::EN);
+        tx.clear(RA::VX, F1::HI);
+        tx.set(RA::VX, F1::B2);
+    });
+    assert_eq!(io.VX.read(), 0b1111);
+    assert_eq!(io.VY.read(), 0x8f);
+}
+
+#[test]
+fn test_try_transaction() {
+    let reg = CountingReg { val: Cell::new(0), reads: Cell::new(0), writes: Cell::new(0) };
+
+    assert_eq!(reg.try_transaction(|tx| {
+        tx.set(F1::B2).put(F1::B1.val(0b10));
+    }), Ok(()));
+    assert_eq!(reg.val.get(), 0b1110);
+    assert_eq!((reg.reads.get(), reg.writes.get()), (1, 1));
+}
+
+struct CountingReg {
+    val: Cell<u32>,
+    reads: Cell<usize>,
+    writes: Cell<usize>
+}
+
+impl InfallibleIO for CountingReg {}
+
+impl ReadableIO<u32, F1::Reg> for CountingReg {
+    fn read(&self) -> u32 {
+        self.reads.set(self.reads.get() + 1);
+        self.val.get()
+    }
+}
+
+impl WritableIO<u32, F1::Reg> for CountingReg {
+    fn write(&self, val: u32) {
+        self.writes.set(self.writes.get() + 1);
+        self.val.set(val);
+    }
+}
+
+reg_bitfields! {
+    F1(u32) [
+        B1 [ 0 => 2 ],
+        B2 [ 2 => 2 ],
+        HI [ 4 => 28 ]
+    ],
+    F2(u8) [
+        EN [ 7 => 1 ]
+    ]
+}
+
+registers_layout! {
+    RA {
+        ( 0x00 => VX: RW<u32, F1::Reg> ),
+        ( 0x04 => VY: RW<u8, F2::Reg> ),
+        @END
+    }
+}